@@ -1,6 +1,6 @@
 extern crate iana_ip_db;
 
-use iana_ip_db::{lookup, Country};
+use iana_ip_db::{classify, Classified, Country, SpecialPurpose};
 
 
 use std::net::IpAddr;
@@ -43,13 +43,26 @@ impl Whitelist {
             return *v;
         }
 
-        match lookup(addr) {
-            Some((_start, _end, cc)) => {
-                let v = self.loc_set.contains(&cc);
+        match classify(addr) {
+            Some(Classified::Delegated(ret)) => {
+                let v = self.loc_set.contains(&ret.country());
                 let act = if v { Action::Reject } else { Action::Accept };
                 self.cache.insert(addr.clone(), act);
                 act
             },
+            Some(Classified::Special(kind, _net)) => {
+                // NOTE: 内网地址直接放行，其它特殊用途地址不应该出现在公网流量中。
+                let act = match kind {
+                    SpecialPurpose::Private
+                    | SpecialPurpose::Shared
+                    | SpecialPurpose::Loopback
+                    | SpecialPurpose::LinkLocal
+                    | SpecialPurpose::UniqueLocal => Action::Accept,
+                    _ => Action::Reject,
+                };
+                self.cache.insert(*addr, act);
+                act
+            },
             None => Action::Unknow,
         }
     }
@@ -88,6 +101,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let ip  = "5.42.250.33".parse::<IpAddr>()?;    // www.moh.gov.sa
     println!("[{:?}] {}", acl.ask(&ip), &ip);
 
+    let ip  = "192.168.1.1".parse::<IpAddr>()?;    // private
+    println!("[{:?}] {}", acl.ask(&ip), &ip);
+
+    let ip  = "192.0.2.1".parse::<IpAddr>()?;      // documentation
+    println!("[{:?}] {}", acl.ask(&ip), &ip);
+
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+extern crate iana_ip_db;
+
+use iana_ip_db::{cidrs_for, Country, IpNet};
+
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cc = std::env::args().nth(1).unwrap_or("CN".to_string());
+    let country = cc.to_uppercase().parse::<Country>()?;
+
+    let name = format!("country-{}", country.code().to_lowercase());
+    let (v4, v6): (Vec<IpNet>, Vec<IpNet>) = cidrs_for(country).partition(|net| net.network().is_ipv4());
+
+    println!("create {}-v4 hash:net family inet", name);
+    for net in v4.iter() {
+        println!("add {}-v4 {}", name, net);
+    }
+
+    println!("create {}-v6 hash:net family inet6", name);
+    for net in v6.iter() {
+        println!("add {}-v6 {}", name, net);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::date::Date;
+use crate::country::Country;
+use crate::registry::Registry;
+use crate::status::Status;
+use crate::error::{ParseError, ParseErrorKind};
+use crate::validate::Delegation;
+
+
+// Format: (first_asn, last_asn, country_index, registry_index << 4 | status_index, date)
+pub type AsnRow = (u32, u32, u8, u8, u32);
+
+
+/// An `asn` record, a range of AS numbers.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct AsnRecord {
+    pub src_registry: Registry,
+    pub country: Country,
+    pub first: u32,
+    pub last: u32,
+    pub status: Status,
+    pub dst_registry: Option<Registry>,
+    pub date: Option<Date>,
+    pub opaque_id: Option<String>,
+    /// Name of the delegated file this record was read from.
+    pub source: &'static str,
+}
+
+impl AsnRecord {
+    // Format: registry_index << 4 | status_index
+    pub fn flags(&self) -> u8 {
+        self.src_registry.index() << 4 | self.status.index()
+    }
+
+    pub fn row(&self) -> AsnRow {
+        (self.first, self.last, self.country.index(), self.flags(),
+         self.date.map(|date| date.number()).unwrap_or(0))
+    }
+}
+
+impl Delegation for AsnRecord {
+    fn range(&self) -> (u128, u128) {
+        (self.first as u128, self.last as u128)
+    }
+
+    fn country(&self) -> Country {
+        self.country
+    }
+
+    fn src_registry(&self) -> Registry {
+        self.src_registry
+    }
+
+    fn status(&self) -> Status {
+        self.status
+    }
+
+    fn date(&self) -> Option<Date> {
+        self.date
+    }
+
+    fn source(&self) -> &'static str {
+        self.source
+    }
+
+    fn slice(&self, first: u128, last: u128) -> Vec<Self> {
+        vec![AsnRecord { first: first as u32, last: last as u32, ..self.clone() }]
+    }
+}
+
+impl fmt::Display for AsnRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} asn {} {} {} {} {} {}",
+            self.src_registry,
+            self.country,
+            self.first,
+            self.last - self.first + 1,
+            self.status,
+            match self.dst_registry {
+                Some(reg) => format!("{}", reg),
+                None => "none".to_string()
+            },
+            match self.date {
+                Some(date) => format!("{}", date),
+                None => "none".to_string()
+            },
+            self.opaque_id.as_deref().unwrap_or("none"))
+    }
+}
+
+impl FromStr for AsnRecord {
+    type Err = ParseError;
+
+    // Same layout as `Record`, `start` is the first AS number and `value` the
+    // number of AS numbers.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split("|").collect();
+        let invalid = |kind: ParseErrorKind, idx: usize| ParseError::new(kind, Some(idx), fields[idx]);
+
+        if fields.len() < 7 {
+            return Err(ParseError::new(ParseErrorKind::Truncated, None, s));
+        }
+        if fields[2] != "asn" {
+            return Err(invalid(ParseErrorKind::Dropped, 2));
+        }
+
+        let src_registry = Registry::from_str(fields[0]).map_err(|e| invalid(e.into(), 0))?;
+        let cc = if fields[1].trim() == "" { "ZZ" } else { fields[1] };
+        let country = Country::from_str(cc).map_err(|e| invalid(e.into(), 1))?;
+
+        let first: u32 = fields[3].parse().map_err(|_| invalid(ParseErrorKind::InvalidValue, 3))?;
+        let nums: u32 = fields[4].parse().map_err(|_| invalid(ParseErrorKind::InvalidValue, 4))?;
+        let last = match nums.checked_sub(1).and_then(|n| first.checked_add(n)) {
+            Some(last) => last,
+            None => return Err(invalid(ParseErrorKind::InvalidValue, 4)),
+        };
+
+        let date = Date::from_str(fields[5]).ok();
+
+        let (status, dst_registry, opaque_id) = if src_registry == Registry::Iana {
+            let idx = if fields.len() == 7 { 6 } else { 7 };
+            let dst_registry = Registry::from_str(fields[idx]).map_err(|e| invalid(e.into(), idx))?;
+            (Status::Assigned, Some(dst_registry), None)
+        } else {
+            let status = Status::from_str(fields[6]).map_err(|e| invalid(e.into(), 6))?;
+            let opaque_id = fields.get(7).map(|s| s.trim()).filter(|s| !s.is_empty()).map(|s| s.to_string());
+            (status, None, opaque_id)
+        };
+
+        Ok(AsnRecord { src_registry, country, first, last, status, dst_registry, date, opaque_id, source: "" })
+    }
+}
+
+
+#[test]
+fn test_asn_record() {
+    let record: AsnRecord = "arin|US|asn|15169|1|20000330|assigned|7ea3a5a3b0f3a4b35b31c0a6a2b4e0f5".parse().unwrap();
+    assert_eq!((record.first, record.last), (15169, 15169));
+    assert_eq!(record.country, Country::US);
+    assert_eq!(record.row(), (15169, 15169, Country::US.index(), 0x20 | Status::Assigned.index(), 20000330));
+    assert_eq!(record.to_string(), "arin US asn 15169 1 assigned none 20000330 7ea3a5a3b0f3a4b35b31c0a6a2b4e0f5");
+
+    let record: AsnRecord = "apnic|JP|asn|173|2|20020801|allocated".parse().unwrap();
+    assert_eq!((record.first, record.last), (173, 174));
+
+    let e = "apnic|JP|asn|173|0|20020801|allocated".parse::<AsnRecord>().unwrap_err();
+    assert_eq!((e.kind, e.field), (ParseErrorKind::InvalidValue, Some(4)));
+    let e = "apnic|JP|asn|4294967295|2|20020801|allocated".parse::<AsnRecord>().unwrap_err();
+    assert_eq!((e.kind, e.field), (ParseErrorKind::InvalidValue, Some(4)));
+    let e = "apnic|JP|ipv4|1.0.1.0|256|20020801|allocated".parse::<AsnRecord>().unwrap_err();
+    assert_eq!(e.kind, ParseErrorKind::Dropped);
+}
@@ -0,0 +1,261 @@
+use smoltcp::wire::{
+    IpAddress,
+    Ipv4Address, Ipv4Cidr,
+    Ipv6Address, Ipv6Cidr,
+};
+
+use std::fmt;
+use std::cmp;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::net;
+
+
+/// Last address of `first/prefix_len`, i.e. `first + 2^(32 - prefix_len) - 1`.
+pub fn v4_last(first: u32, prefix_len: u8) -> u32 {
+    assert!(prefix_len <= 32);
+    let host_mask = u32::MAX.checked_shr(prefix_len as u32).unwrap_or(0);
+    first | host_mask
+}
+
+/// Last address of `first/prefix_len`, i.e. `first + 2^(128 - prefix_len) - 1`.
+pub fn v6_last(first: u128, prefix_len: u8) -> u128 {
+    assert!(prefix_len <= 128);
+    let host_mask = u128::MAX.checked_shr(prefix_len as u32).unwrap_or(0);
+    first | host_mask
+}
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd)]
+pub struct Ipv4Range {
+    pub start_ip: Ipv4Address,
+    pub end_ip  : Ipv4Address,
+}
+
+impl Ord for Ipv4Range {
+    fn cmp(&self, other: &Ipv4Range) -> cmp::Ordering {
+        self.start_ip.cmp(&other.start_ip)
+    }
+}
+
+impl fmt::Display for Ipv4Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}", self.start_ip, self.end_ip)
+    }
+}
+
+impl Ipv4Range {
+    pub fn new(start_ip: Ipv4Address, end_ip: Ipv4Address) -> Self {
+        Ipv4Range { start_ip, end_ip }
+    }
+    
+    pub fn with_nums(start_ip: Ipv4Address, nums: u32) -> Self {
+        let end_ip_number = u32::from(Ipv4Addr::from(start_ip.0)) + (nums - 1);
+        let end_ip = Ipv4Address( Ipv4Addr::from(end_ip_number).octets() );
+
+        Ipv4Range { start_ip, end_ip }
+    }
+
+    pub fn first(&self) -> Ipv4Address {
+        self.start_ip
+    }
+
+    pub fn last(&self) -> Ipv4Address {
+        self.end_ip
+    }
+
+    pub fn total(&self) -> u32 {
+        u32::from(Ipv4Addr::from(self.end_ip.0)) - u32::from(Ipv4Addr::from(self.start_ip.0)) + 1
+    }
+
+    pub fn addrs(&self) -> Ipv4AddrsIter {
+        Ipv4AddrsIter {
+            offset: u32::from(Ipv4Addr::from(self.start_ip.0)) as u64,
+            end   : u32::from(Ipv4Addr::from(self.end_ip.0)) as u64,
+        }
+    }
+
+    pub fn cidrs(&self) -> Ipv4CidrIter {
+        Ipv4CidrIter {
+            inner: net::IpNetIter::new(IpAddr::from(Ipv4Addr::from(self.start_ip.0)),
+                                       IpAddr::from(Ipv4Addr::from(self.end_ip.0))),
+        }
+    }
+}
+
+pub struct Ipv4AddrsIter {
+    offset: u64,
+    end: u64,
+}
+
+impl Iterator for Ipv4AddrsIter {
+    type Item = Ipv4Address;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.end >= self.offset {
+            let ip = Ipv4Addr::from(self.offset as u32);
+            self.offset += 1;
+            Some(Ipv4Address(ip.octets()))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Ipv4CidrIter {
+    inner: net::IpNetIter,
+}
+
+impl Iterator for Ipv4CidrIter {
+    type Item = Ipv4Cidr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let net = self.inner.next()?;
+        match net.network() {
+            IpAddr::V4(v4_addr) => Some(Ipv4Cidr::new(Ipv4Address(v4_addr.octets()), net.prefix_len())),
+            IpAddr::V6(_) => unreachable!(),
+        }
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IpBlock {
+    Ipv4Range(Ipv4Range),
+    Ipv4Cidr(Ipv4Cidr),
+    Ipv6Cidr(Ipv6Cidr),
+}
+
+impl IpBlock {
+    pub fn first(&self) -> IpAddress {
+        match *self {
+            IpBlock::Ipv4Range(v4_range) => IpAddress::Ipv4(v4_range.first()),
+            IpBlock::Ipv4Cidr(v4_cidr) => {
+                IpAddress::from(v4_cidr.network().address())
+            },
+            IpBlock::Ipv6Cidr(v6_cidr) => {
+                IpAddress::from(v6_cidr.address())
+            },
+        }
+    }
+
+    pub fn last(&self) -> IpAddress {
+        match *self {
+            IpBlock::Ipv4Range(v4_range) => IpAddress::Ipv4(v4_range.last()),
+            IpBlock::Ipv4Cidr(v4_cidr) => {
+                let first_number = u32::from(Ipv4Addr::from(v4_cidr.network().address()));
+                let last_number = v4_last(first_number, v4_cidr.prefix_len());
+                IpAddress::from(Ipv4Address::from(Ipv4Addr::from(last_number)))
+            },
+            IpBlock::Ipv6Cidr(v6_cidr) => {
+                let first_number = u128::from(Ipv6Addr::from(v6_cidr.address().0));
+                let last_number = v6_last(first_number, v6_cidr.prefix_len());
+                IpAddress::from(Ipv6Address::from(Ipv6Addr::from(last_number)))
+            },
+        }
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        match *self {
+            IpBlock::Ipv4Range(_) | IpBlock::Ipv4Cidr(_) => true,
+            IpBlock::Ipv6Cidr(_) => false,
+        }
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        match *self {
+            IpBlock::Ipv4Range(_) | IpBlock::Ipv4Cidr(_) => false,
+            IpBlock::Ipv6Cidr(_) => true,
+        }
+    }
+}
+
+
+impl fmt::Display for IpBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IpBlock::Ipv4Range(v4_range) => fmt::Display::fmt(&v4_range, f),
+            IpBlock::Ipv4Cidr(v4_cidr) => fmt::Display::fmt(&v4_cidr, f),
+            IpBlock::Ipv6Cidr(v6_cidr) => fmt::Display::fmt(&v6_cidr, f),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn v6_block(addr: u128, prefix_len: u8) -> (u128, u128) {
+        let mask = if prefix_len == 0 { 0 } else { u128::MAX << (128 - prefix_len as u32) };
+        let start_ip = Ipv6Address(Ipv6Addr::from(addr & mask).octets());
+        let block = IpBlock::Ipv6Cidr(Ipv6Cidr::new(start_ip, prefix_len));
+
+        match (block.first(), block.last()) {
+            (IpAddress::Ipv6(first), IpAddress::Ipv6(last)) => {
+                (u128::from(Ipv6Addr::from(first.0)), u128::from(Ipv6Addr::from(last.0)))
+            },
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_ipv6_block_last() {
+        // 2001:218::/32
+        let (first, last) = v6_block(0x2001_0218 << 96, 32);
+        assert_eq!(Ipv6Addr::from(first), "2001:218::".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(Ipv6Addr::from(last), "2001:218:ffff:ffff:ffff:ffff:ffff:ffff".parse::<Ipv6Addr>().unwrap());
+
+        assert_eq!(v6_block(0, 0), (0, u128::MAX));
+        assert_eq!(v6_block(1, 128), (1, 1));
+    }
+
+    #[test]
+    fn test_ipv4_cidr_last() {
+        let block = IpBlock::Ipv4Cidr(Ipv4Cidr::new(Ipv4Address([1, 0, 2, 0]), 23));
+        assert_eq!(block.last(), IpAddress::Ipv4(Ipv4Address([1, 0, 3, 255])));
+
+        let block = IpBlock::Ipv4Cidr(Ipv4Cidr::new(Ipv4Address([0, 0, 0, 0]), 0));
+        assert_eq!(block.last(), IpAddress::Ipv4(Ipv4Address([255, 255, 255, 255])));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_ipv6_block_matches_prefix(addr in any::<u128>(), prefix_len in 0u8..=128) {
+            let (first, last) = v6_block(addr, prefix_len);
+            let host_bits = 128 - prefix_len as u32;
+
+            // Same prefix, every host bit of `first` clear and of `last` set.
+            prop_assert_eq!(first.checked_shr(host_bits).unwrap_or(0), last.checked_shr(host_bits).unwrap_or(0));
+            prop_assert_eq!(last - first, if host_bits == 128 { u128::MAX } else { (1u128 << host_bits) - 1 });
+            // The block is exactly one prefix.
+            let nets = net::IpNetIter::new(IpAddr::from(Ipv6Addr::from(first)), IpAddr::from(Ipv6Addr::from(last)));
+            prop_assert_eq!(nets.map(|net| net.prefix_len()).collect::<Vec<u8>>(), vec![prefix_len]);
+        }
+
+        #[test]
+        fn prop_ipv4_cidr_matches_prefix(addr in any::<u32>(), prefix_len in 0u8..=32) {
+            let block = IpBlock::Ipv4Cidr(Ipv4Cidr::new(Ipv4Address(Ipv4Addr::from(addr).octets()), prefix_len));
+            let (first, last) = match (block.first(), block.last()) {
+                (IpAddress::Ipv4(first), IpAddress::Ipv4(last)) => {
+                    (u32::from(Ipv4Addr::from(first)) as u64, u32::from(Ipv4Addr::from(last)) as u64)
+                },
+                _ => unreachable!()
+            };
+
+            prop_assert_eq!(last - first + 1, 1u64 << (32 - prefix_len as u32));
+        }
+
+        #[test]
+        fn prop_ipv4_range_total(start in any::<u32>(), nums in 1u32..=u32::MAX) {
+            prop_assume!(start.checked_add(nums - 1).is_some());
+
+            let range = Ipv4Range::with_nums(Ipv4Address(Ipv4Addr::from(start).octets()), nums);
+            prop_assert_eq!(range.total(), nums);
+
+            let cidrs = range.cidrs().map(|cidr| 1u64 << (32 - cidr.prefix_len() as u32)).sum::<u64>();
+            prop_assert_eq!(cidrs, nums as u64);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::net::{IpNet, IpNetIter};
+use crate::special::{SPECIAL_V4, SPECIAL_V6};
+
+
+/// Address space painted as bogon or not, later calls to `set` win.
+#[derive(Debug, Clone)]
+pub struct Bogons {
+    // segment start => bogon, a segment ends where the next one starts.
+    segments: BTreeMap<u128, bool>,
+    max: u128,
+}
+
+impl Bogons {
+    /// The whole address space `0 ..= max` starts as bogon.
+    pub fn new(max: u128) -> Self {
+        let mut segments = BTreeMap::new();
+        segments.insert(0, true);
+
+        Bogons { segments, max }
+    }
+
+    pub fn set(&mut self, first: u128, last: u128, bogon: bool) {
+        assert!(first <= last && last <= self.max);
+
+        if last < self.max {
+            let after = self.get(last + 1);
+            self.segments.insert(last + 1, after);
+        }
+
+        let inner = self.segments.range(first..=last).map(|(&start, _)| start).collect::<Vec<u128>>();
+        for start in inner {
+            self.segments.remove(&start);
+        }
+        self.segments.insert(first, bogon);
+    }
+
+    fn get(&self, number: u128) -> bool {
+        self.segments.range(..=number).next_back().map(|(_, &bogon)| bogon).unwrap_or(true)
+    }
+
+    /// Bogon ranges, sorted, adjacent ranges are joined.
+    pub fn ranges(&self) -> Vec<(u128, u128)> {
+        let mut ranges: Vec<(u128, u128)> = Vec::new();
+
+        let mut iter = self.segments.iter().peekable();
+        while let Some((&start, &bogon)) = iter.next() {
+            let end = match iter.peek() {
+                Some((&next, _)) => next - 1,
+                None => self.max,
+            };
+            if !bogon {
+                continue;
+            }
+
+            match ranges.last_mut() {
+                Some(last) if last.1 + 1 == start => last.1 = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        ranges
+    }
+}
+
+
+// Unallocated IANA space, then RIR available/reserved blocks, then the
+// special-purpose blocks from the least to the most specific.
+fn build(max: u128, iana: &[(u128, u128)], unallocated: &[(u128, u128)], special: &[(u128, u128, bool)]) -> Bogons {
+    let mut bogons = Bogons::new(max);
+    for &(first, last) in iana.iter() {
+        bogons.set(first, last, false);
+    }
+    for &(first, last) in unallocated.iter() {
+        bogons.set(first, last, true);
+    }
+    for &(first, last, bogon) in special.iter() {
+        bogons.set(first, last, bogon);
+    }
+
+    bogons
+}
+
+/// IPv4 full bogons as a minimal list of prefixes.
+///
+/// `iana` is the space IANA delegated to the RIRs, `unallocated` the RIR
+/// `available` and `reserved` blocks.
+pub fn bogons_v4(iana: &[(u128, u128)], unallocated: &[(u128, u128)]) -> Vec<IpNet> {
+    let mut special = SPECIAL_V4.iter()
+        .map(|&(network, prefix_len, kind)| {
+            let last = network | u32::MAX.checked_shr(prefix_len as u32).unwrap_or(0);
+            (prefix_len, network as u128, last as u128, kind.is_bogon())
+        })
+        .collect::<Vec<(u8, u128, u128, bool)>>();
+    special.sort_by_key(|&(prefix_len, network, _, _)| (prefix_len, network));
+    let special = special.iter().map(|&(_, first, last, bogon)| (first, last, bogon)).collect::<Vec<_>>();
+
+    build(u32::MAX as u128, iana, unallocated, &special).ranges().iter()
+        .flat_map(|&(first, last)| {
+            IpNetIter::new(IpAddr::from(Ipv4Addr::from(first as u32)), IpAddr::from(Ipv4Addr::from(last as u32)))
+        })
+        .collect()
+}
+
+/// IPv6 full bogons as a minimal list of prefixes, see `bogons_v4`.
+pub fn bogons_v6(iana: &[(u128, u128)], unallocated: &[(u128, u128)]) -> Vec<IpNet> {
+    let mut special = SPECIAL_V6.iter()
+        .map(|&(network, prefix_len, kind)| {
+            let last = network | u128::MAX.checked_shr(prefix_len as u32).unwrap_or(0);
+            (prefix_len, network, last, kind.is_bogon())
+        })
+        .collect::<Vec<(u8, u128, u128, bool)>>();
+    special.sort_by_key(|&(prefix_len, network, _, _)| (prefix_len, network));
+    let special = special.iter().map(|&(_, first, last, bogon)| (first, last, bogon)).collect::<Vec<_>>();
+
+    build(u128::MAX, iana, unallocated, &special).ranges().iter()
+        .flat_map(|&(first, last)| {
+            IpNetIter::new(IpAddr::from(Ipv6Addr::from(first)), IpAddr::from(Ipv6Addr::from(last)))
+        })
+        .collect()
+}
+
+
+#[test]
+fn test_bogons_set() {
+    let mut bogons = Bogons::new(255);
+    assert_eq!(bogons.ranges(), vec![(0, 255)]);
+
+    bogons.set(16, 127, false);
+    bogons.set(32, 47, true);
+    bogons.set(200, 255, false);
+    assert_eq!(bogons.ranges(), vec![(0, 15), (32, 47), (128, 199)]);
+
+    bogons.set(48, 63, true);
+    bogons.set(0, 15, false);
+    assert_eq!(bogons.ranges(), vec![(32, 63), (128, 199)]);
+
+    bogons.set(0, 255, true);
+    assert_eq!(bogons.ranges(), vec![(0, 255)]);
+}
+
+#[test]
+fn test_bogons_v4() {
+    let range = |first: &str, last: &str| {
+        (u32::from(first.parse::<Ipv4Addr>().unwrap()) as u128, u32::from(last.parse::<Ipv4Addr>().unwrap()) as u128)
+    };
+    let iana = [
+        range("1.0.0.0", "1.255.255.255"),
+        range("8.0.0.0", "15.255.255.255"),
+        range("192.0.0.0", "192.255.255.255"),
+    ];
+    let unallocated = [range("1.0.0.0", "1.0.0.255")];
+
+    let bogons = bogons_v4(&iana, &unallocated).iter().map(|net| net.to_string()).collect::<Vec<String>>();
+    assert_eq!(&bogons[..3], &["0.0.0.0/8", "1.0.0.0/24", "2.0.0.0/7"]);
+    assert!(bogons.contains(&"10.0.0.0/8".to_string()));
+    assert!(bogons.contains(&"16.0.0.0/4".to_string()));
+    assert!(bogons.contains(&"192.168.0.0/16".to_string()));
+    assert!(bogons.contains(&"192.0.2.0/24".to_string()));
+    assert!(bogons.contains(&"224.0.0.0/3".to_string()));
+    assert!(!bogons.iter().any(|net| net.starts_with("8.")));
+    // AS112 anycast
+    assert!(!bogons.contains(&"192.31.196.0/24".to_string()));
+}
+
+#[test]
+fn test_bogons_v6() {
+    let range = |first: &str, prefix_len: u32| {
+        let first = u128::from(first.parse::<Ipv6Addr>().unwrap());
+        (first, first | u128::MAX >> prefix_len)
+    };
+    let iana = [range("2001:200::", 23), range("2400::", 12)];
+
+    let nets = bogons_v6(&iana, &[]);
+    let is_bogon = |ip: &str| nets.iter().any(|net| net.contains(&ip.parse().unwrap()));
+    let bogons = nets.iter().map(|net| net.to_string()).collect::<Vec<String>>();
+    assert_eq!(bogons[0], "::/10");
+    assert!(bogons.contains(&"2000::/16".to_string()));
+    assert!(bogons.contains(&"8000::/1".to_string()));
+    assert!(is_bogon("::1"));
+    assert!(is_bogon("2001:db8::1"));
+    assert!(is_bogon("2001:100::1"));
+    assert!(is_bogon("fd00::1"));
+    // NAT64, Teredo and 6to4 are not bogons.
+    assert!(!is_bogon("64:ff9b::808:808"));
+    assert!(!is_bogon("2001::1"));
+    assert!(!is_bogon("2002::1"));
+    assert!(!is_bogon("2001:200::1"));
+    assert!(!is_bogon("2400:cb00::1"));
+}
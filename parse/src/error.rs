@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::status::InvalidStatus;
+use crate::country::InvalidCountryCode;
+use crate::registry::InvalidRegistry;
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    InvalidStatus,
+    InvalidCountryCode,
+    InvalidRegistry,
+    /// Malformed start address.
+    InvalidAddress,
+    /// Malformed, zero or out of range `value` field.
+    InvalidValue,
+    Unrecognized,
+    /// Not an IPv4 or IPv6 record, e.g. ASN.
+    Dropped,
+    Truncated,
+    /// Malformed version line.
+    InvalidHeader,
+    /// Malformed summary line.
+    InvalidSummary,
+    /// The version or a summary line disagrees with the records in the file.
+    CountMismatch,
+}
+
+impl From<InvalidStatus> for ParseErrorKind {
+    fn from(_src: InvalidStatus) -> ParseErrorKind {
+        ParseErrorKind::InvalidStatus
+    }
+}
+impl From<InvalidCountryCode> for ParseErrorKind {
+    fn from(_src: InvalidCountryCode) -> ParseErrorKind {
+        ParseErrorKind::InvalidCountryCode
+    }
+}
+impl From<InvalidRegistry> for ParseErrorKind {
+    fn from(_src: InvalidRegistry) -> ParseErrorKind {
+        ParseErrorKind::InvalidRegistry
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+/// A record line that could not be parsed.
+///
+/// `Record::from_str` leaves `file` and `line` empty, `parse` fills them in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Name of the delegated file.
+    pub file: &'static str,
+    /// 1-based line number, 0 if unknown.
+    pub line: usize,
+    /// 0-based index of the offending `|` separated field, `None` for the whole line.
+    pub field: Option<usize>,
+    /// The offending field, the whole line, or a description for `CountMismatch`.
+    pub text: String,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, field: Option<usize>, text: &str) -> Self {
+        ParseError { kind, file: "", line: 0, field, text: text.to_string() }
+    }
+
+    pub fn at(mut self, file: &'static str, line: usize) -> Self {
+        self.file = file;
+        self.line = line;
+        self
+    }
+
+    /// Lines that used to be reported and skipped even without `--lenient`.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self.kind, ParseErrorKind::InvalidCountryCode | ParseErrorKind::Truncated | ParseErrorKind::Dropped)
+    }
+}
+
+impl std::error::Error for ParseError { }
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.kind)?;
+        match self.field {
+            Some(field) => write!(f, " (field {}): {:?}", field, self.text),
+            None => write!(f, ": {:?}", self.text),
+        }
+    }
+}
+
+
+/// Errors collected by `parse`.
+#[derive(Debug, Clone, Default)]
+pub struct ParseReport {
+    pub errors: Vec<ParseError>,
+}
+
+impl ParseReport {
+    pub fn count(&self, kind: ParseErrorKind) -> usize {
+        self.errors.iter().filter(|e| e.kind == kind).count()
+    }
+
+    /// Print every error, then a summary line of the non-zero counts.
+    pub fn print(&self) {
+        for e in self.errors.iter() {
+            println!("[ERROR] {}", e);
+        }
+
+        let kinds = [
+            ParseErrorKind::InvalidStatus,
+            ParseErrorKind::InvalidCountryCode,
+            ParseErrorKind::InvalidRegistry,
+            ParseErrorKind::InvalidAddress,
+            ParseErrorKind::InvalidValue,
+            ParseErrorKind::Unrecognized,
+            ParseErrorKind::Truncated,
+            ParseErrorKind::InvalidHeader,
+            ParseErrorKind::InvalidSummary,
+            ParseErrorKind::CountMismatch,
+        ];
+        let counts = kinds.iter()
+            .map(|&kind| (kind, self.count(kind)))
+            .filter(|&(_, n)| n > 0)
+            .map(|(kind, n)| format!("{} {}", n, kind))
+            .collect::<Vec<String>>();
+
+        if counts.is_empty() {
+            println!("parse: no errors");
+        } else {
+            println!("parse: {} errors, {}", self.errors.len(), counts.join(", "));
+        }
+    }
+}
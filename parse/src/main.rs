@@ -1,10 +1,11 @@
 extern crate getopts;
 extern crate smoltcp;
+extern crate sources;
 
 
 use smoltcp::wire::{
     IpAddress,
-    Ipv4Address, Ipv4Cidr,
+    Ipv4Address,
     Ipv6Address, Ipv6Cidr,
 };
 
@@ -12,274 +13,65 @@ use smoltcp::wire::{
 use std::fmt;
 use std::cmp;
 use std::str::FromStr;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::io::{Write, Read};
+use std::io::{self, Write, Read};
 use std::fs::{self, File, OpenOptions};
-use std::collections::HashSet;
 use std::net::{Ipv4Addr, Ipv6Addr};
 
 
+#[allow(dead_code)]
+#[path = "../../src/status.rs"]
 mod status;
 #[path = "../../src/country.rs"]
 mod country;
+#[path = "../../src/registry.rs"]
 mod registry;
-
-use self::status::{Status, InvalidStatus};
-use self::country::{Country, InvalidCountryCode};
-use self::registry::{Registry, InvalidRegistry};
-
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-pub enum ParseError {
-    InvalidStatus,
-    InvalidCountryCode,
-    InvalidRegistry,
-    Unrecognized,
-    Dropped,
-    Truncated,
-}
-
-impl std::error::Error for ParseError { }
-
-impl From<InvalidStatus> for ParseError {
-    fn from(_src: InvalidStatus) -> ParseError {
-        ParseError::InvalidStatus
-    }
-}
-impl From<InvalidCountryCode> for ParseError {
-    fn from(_src: InvalidCountryCode) -> ParseError {
-        ParseError::InvalidCountryCode
-    }
-}
-impl From<InvalidRegistry> for ParseError {
-    fn from(_src: InvalidRegistry) -> ParseError {
-        ParseError::InvalidRegistry
-    }
-}
-
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-
-// Files not exists
-// ("delegated-arin-latest",             "https://ftp.arin.net/pub/stats/arin/delegated-arin-latest"),
-// ("delegated-iana-extended-latest",    "ftp://ftp.apnic.net/public/stats/iana/delegated-iana-extended-latest"),
-pub const IANA_RIR_FILES: [(&str, &str); 10] = [
-    ("delegated-arin-extended-latest",    "https://ftp.arin.net/pub/stats/arin/delegated-arin-extended-latest"),
-    ("delegated-ripencc-latest",          "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-latest"),
-    ("delegated-ripencc-extended-latest", "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-extended-latest"),
-    ("delegated-apnic-latest",            "https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"),
-    ("delegated-apnic-extended-latest",   "https://ftp.apnic.net/stats/apnic/delegated-apnic-extended-latest"),
-    ("delegated-lacnic-latest",           "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-latest"),
-    ("delegated-lacnic-extended-latest",  "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-extended-latest"),
-    ("delegated-afrinic-latest",          "https://ftp.afrinic.net/pub/stats/afrinic/delegated-afrinic-latest"),
-    ("delegated-afrinic-extended-latest", "https://ftp.afrinic.net/pub/stats/afrinic/delegated-afrinic-extended-latest"),
-    ("delegated-iana-latest",             "https://ftp.apnic.net/stats/iana/delegated-iana-latest"),
-];
-
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd)]
-pub struct Ipv4Range {
-    pub start_ip: Ipv4Address,
-    pub end_ip  : Ipv4Address,
-}
-
-impl Ord for Ipv4Range {
-    fn cmp(&self, other: &Ipv4Range) -> cmp::Ordering {
-        self.start_ip.cmp(&other.start_ip)
-    }
-}
-
-impl fmt::Display for Ipv4Range {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} - {}", self.start_ip, self.end_ip)
-    }
-}
-
-impl Ipv4Range {
-    pub fn new(start_ip: Ipv4Address, end_ip: Ipv4Address) -> Self {
-        Ipv4Range { start_ip, end_ip }
-    }
-    
-    pub fn with_nums(start_ip: Ipv4Address, nums: u32) -> Self {
-        let end_ip_number = u32::from(Ipv4Addr::from(start_ip.0)) + (nums - 1);
-        let end_ip = Ipv4Address( Ipv4Addr::from(end_ip_number).octets() );
-
-        Ipv4Range { start_ip, end_ip }
-    }
-
-    pub fn first(&self) -> Ipv4Address {
-        self.start_ip
-    }
-
-    pub fn last(&self) -> Ipv4Address {
-        self.end_ip
-    }
-
-    pub fn total(&self) -> u32 {
-        u32::from(Ipv4Addr::from(self.end_ip.0)) - u32::from(Ipv4Addr::from(self.start_ip.0)) + 1
-    }
-
-    pub fn addrs(&self) -> Ipv4AddrsIter {
-        Ipv4AddrsIter {
-            offset: u32::from(Ipv4Addr::from(self.start_ip.0)) as u64,
-            end   : u32::from(Ipv4Addr::from(self.end_ip.0)) as u64,
-        }
-    }
-
-    pub fn cidrs(&self) -> Ipv4CidrIter {
-        Ipv4CidrIter {
-            start: u32::from(Ipv4Addr::from(self.start_ip.0)) as u64,
-            end  : u32::from(Ipv4Addr::from(self.end_ip.0)) as u64,
-        }
-    }
-}
-
-pub struct Ipv4AddrsIter {
-    offset: u64,
-    end: u64,
-}
-
-impl Iterator for Ipv4AddrsIter {
-    type Item = Ipv4Address;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.end >= self.offset {
-            let ip = Ipv4Addr::from(self.offset as u32);
-            self.offset += 1;
-            Some(Ipv4Address(ip.octets()))
-        } else {
-            None
-        }
-    }
-}
-
-pub struct Ipv4CidrIter {
-    start: u64,
-    end  : u64,
-}
-
-impl Iterator for Ipv4CidrIter {
-    type Item = Ipv4Cidr;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.end > self.start {
-            let mut shift = cmp::min(32, self.start.trailing_zeros());
-            let num: u64;
-
-            loop {
-                let n = 2u64.pow(shift);
-                if self.start + n > self.end + 1 {
-                    if shift == 0 {
-                        panic!("oops ...")
-                    }
-                    shift -= 1;
-                } else {
-                    num = n;
-                    break;
-                }
-            }
-            let prefix_len = 32 - shift;
-            let ip = Ipv4Addr::from(self.start as u32);
-            let cidr = Ipv4Cidr::new(Ipv4Address(ip.octets()), prefix_len as u8);
-            self.start += num;
-            Some(cidr)
-        } else if self.end == self.start {
-            let ip = Ipv4Addr::from(self.end as u32);
-            let cidr = Ipv4Cidr::new(Ipv4Address(ip.octets()), 32);
-            self.start += 1;
-            Some(cidr)
-        } else {
-            None
-        }
-    }
-}
-
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub enum IpBlock {
-    Ipv4Range(Ipv4Range),
-    Ipv4Cidr(Ipv4Cidr),
-    Ipv6Cidr(Ipv6Cidr),
-}
-
-impl IpBlock {
-    pub fn first(&self) -> IpAddress {
-        match *self {
-            IpBlock::Ipv4Range(v4_range) => IpAddress::Ipv4(v4_range.first()),
-            IpBlock::Ipv4Cidr(v4_cidr) => {
-                IpAddress::from(v4_cidr.network().address())
-            },
-            IpBlock::Ipv6Cidr(v6_cidr) => {
-                IpAddress::from(v6_cidr.address())
-            },
-        }
-    }
-
-    pub fn last(&self) -> IpAddress {
-        match *self {
-            IpBlock::Ipv4Range(v4_range) => IpAddress::Ipv4(v4_range.last()),
-            IpBlock::Ipv4Cidr(v4_cidr) => {
-                
-                let first_number = match self.first() {
-                    IpAddress::Ipv4(v4_addr) => u32::from(Ipv4Addr::from(v4_addr)),
-                    IpAddress::Ipv6(_) => unreachable!(),
-                    _ => unreachable!()
-                };
-
-                let max = first_number + 2u32.pow(v4_cidr.prefix_len() as u32);
-                IpAddress::from(Ipv4Address::from(Ipv4Addr::from(max)))
-            },
-            IpBlock::Ipv6Cidr(v6_cidr) => {
-                let first_number = match self.first() {
-                    IpAddress::Ipv4(_) => unreachable!(),
-                    IpAddress::Ipv6(v6_addr) => u128::from(Ipv6Addr::from(v6_addr)),
-                    _ => unreachable!()
-                };
-
-                let max = first_number + 2u128.pow(v6_cidr.prefix_len() as u32);
-                IpAddress::from(Ipv6Address::from(Ipv6Addr::from(max)))
-            },
-        }
-    }
-
-    pub fn is_ipv4(&self) -> bool {
-        match *self {
-            IpBlock::Ipv4Range(_) | IpBlock::Ipv4Cidr(_) => true,
-            IpBlock::Ipv6Cidr(_) => false,
-        }
-    }
-
-    pub fn is_ipv6(&self) -> bool {
-        match *self {
-            IpBlock::Ipv4Range(_) | IpBlock::Ipv4Cidr(_) => false,
-            IpBlock::Ipv6Cidr(_) => true,
-        }
-    }
-}
-
-
-impl fmt::Display for IpBlock {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            IpBlock::Ipv4Range(v4_range) => fmt::Display::fmt(&v4_range, f),
-            IpBlock::Ipv4Cidr(v4_cidr) => fmt::Display::fmt(&v4_cidr, f),
-            IpBlock::Ipv6Cidr(v6_cidr) => fmt::Display::fmt(&v6_cidr, f),
-        }
-    }
-}
-
-
-#[derive(Debug, Copy, Clone, Hash, Eq)]
+#[allow(dead_code)]
+#[path = "../../src/special.rs"]
+mod special;
+mod asn;
+mod block;
+mod bogon;
+mod error;
+mod merge;
+mod stats;
+mod validate;
+#[path = "../../src/date.rs"]
+mod date;
+#[allow(dead_code)]
+#[path = "../../src/format.rs"]
+mod format;
+#[allow(dead_code)]
+#[path = "../../src/net.rs"]
+mod net;
+
+use self::status::Status;
+use self::country::{Country, COUNTRY_CODES};
+use self::registry::Registry;
+use self::date::Date;
+use self::merge::{MergePolicy, Ipv4Row, Ipv6Row};
+use self::validate::{Delegation, Resolution};
+use self::asn::{AsnRecord, AsnRow};
+use self::block::{IpBlock, Ipv4Range};
+use self::error::{ParseError, ParseErrorKind, ParseReport};
+use self::stats::{FileHeader, Summary};
+use sources::{Source, Sources};
+
+
+#[derive(Debug, Clone, Hash, Eq)]
 pub struct Record {
     pub src_registry: Registry,
     pub country: Country,
     pub ip_block: IpBlock,
     pub status: Status,
     pub dst_registry: Option<Registry>,
+    pub date: Option<Date>,
+    /// Holder id from the `*-extended-latest` files, shared by all the
+    /// resources of one organisation in a registry.
+    pub opaque_id: Option<String>,
+    /// Name of the delegated file this record was read from.
+    pub source: &'static str,
 }
 
 impl Record {
@@ -317,6 +109,23 @@ impl Record {
         self.dst_registry
     }
 
+    pub fn date(&self) -> Option<Date> {
+        self.date
+    }
+
+    pub fn opaque_id(&self) -> Option<&str> {
+        self.opaque_id.as_deref()
+    }
+
+    // Format: registry_index << 4 | status_index
+    pub fn flags(&self) -> u8 {
+        self.src_registry.index() << 4 | self.status.index()
+    }
+
+    pub fn date_number(&self) -> u32 {
+        self.date.map(|date| date.number()).unwrap_or(0)
+    }
+
     pub fn is_ipv4(&self) -> bool {
         self.ip_block.is_ipv4()
     }
@@ -325,47 +134,37 @@ impl Record {
         self.ip_block.is_ipv6()
     }
 
-    pub fn codegen(&self) -> String {
-        let first_ip = self.ip_block.first();
-        let last_ip = self.ip_block.last();
-        
-        let ip_to_number_string = |ipaddr| -> String {
+    /// `(first_ip, last_ip)` as numbers, IPv4 addresses are zero-extended.
+    pub fn range(&self) -> (u128, u128) {
+        let to_number = |ipaddr| -> u128 {
             match ipaddr {
-                IpAddress::Ipv4(v4_addr) => format!("{}", u32::from(Ipv4Addr::from(v4_addr))),
-                IpAddress::Ipv6(v6_addr) => format!("{}", u128::from(Ipv6Addr::from(v6_addr.0))),
+                IpAddress::Ipv4(v4_addr) => u32::from(Ipv4Addr::from(v4_addr)) as u128,
+                IpAddress::Ipv6(v6_addr) => u128::from(Ipv6Addr::from(v6_addr.0)),
                 _ => unreachable!()
             }
         };
-        
-        format!("({}, {}, {})",
-                ip_to_number_string(first_ip),
-                ip_to_number_string(last_ip),
-                self.country.index())
+
+        (to_number(self.ip_block.first()), to_number(self.ip_block.last()))
     }
 
-    pub fn codegen_c(&self) -> String {
-        let first_ip = self.ip_block.first();
-        let last_ip = self.ip_block.last();
-        
-        let ip_to_number_string = |ipaddr| -> String {
-            match ipaddr {
-                IpAddress::Ipv4(v4_addr) => format!("{}", u32::from(Ipv4Addr::from(v4_addr))),
-                IpAddress::Ipv6(v6_addr) => {
-                    // ((__uint128_t)0x75f17d6b3588f843 << 64) | 0xb13dea7c9c324e51
-                    let n = u128::from(Ipv6Addr::from(v6_addr.0));
-                    let hi = (n >> 64) as u64;
-                    let lo = n as u64;
-                    // format!("((__uint128_t){} << 64) | (__uint64_t){}", hi, lo)
-                    format!("U128({}U, {}U)", hi, lo)
-                },
-                _ => unreachable!()
-            }
-        };
-        
-        format!("{{ {}, {}, {} }}",
-                ip_to_number_string(first_ip),
-                ip_to_number_string(last_ip),
-                self.country.index())
+    pub fn ipv4_row(&self) -> (u32, u32, u8, u8, u32) {
+        match (self.ip_block.first(), self.ip_block.last()) {
+            (IpAddress::Ipv4(first), IpAddress::Ipv4(last)) => {
+                (u32::from(Ipv4Addr::from(first)), u32::from(Ipv4Addr::from(last)),
+                 self.country.index(), self.flags(), self.date_number())
+            },
+            _ => unreachable!()
+        }
+    }
+
+    pub fn ipv6_row(&self) -> (u128, u128, u8, u8, u32) {
+        match (self.ip_block.first(), self.ip_block.last()) {
+            (IpAddress::Ipv6(first), IpAddress::Ipv6(last)) => {
+                (u128::from(Ipv6Addr::from(first.0)), u128::from(Ipv6Addr::from(last.0)),
+                 self.country.index(), self.flags(), self.date_number())
+            },
+            _ => unreachable!()
+        }
     }
 }
 
@@ -390,7 +189,7 @@ impl PartialEq for Record {
 
 impl fmt::Display for Record {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {} {} {} {} {}",
+        write!(f, "{} {} {} {} {} {} {} {}",
             self.src_registry,
             self.country,
             self.type_(),
@@ -403,7 +202,12 @@ impl fmt::Display for Record {
             match self.dst_registry {
                 Some(reg) => format!("{}", reg),
                 None => "none".to_string()
-            })
+            },
+            match self.date {
+                Some(date) => format!("{}", date),
+                None => "none".to_string()
+            },
+            self.opaque_id().unwrap_or("none"))
     }
 }
 
@@ -417,95 +221,240 @@ impl FromStr for Record {
         // Format:
         // 
         //      registry | cc | type | start | value | date | status [ | extensions ... ]
+        //
+        // The `*-extended-latest` files put an opaque-id as the first extension,
+        // the IANA file puts the registry it delegated to there.
         let fields: Vec<&str> = s.split("|").collect();
-        
+        let invalid = |kind: ParseErrorKind, idx: usize| ParseError::new(kind, Some(idx), fields[idx]);
+
         if fields.len() < 7 {
             // Less than 7 fields length of this RIR Record
-            return Err(ParseError::Truncated);
+            return Err(ParseError::new(ParseErrorKind::Truncated, None, s));
         }
         
-        let src_registry = Registry::from_str(fields[0])?;
+        let src_registry = Registry::from_str(fields[0]).map_err(|e| invalid(e.into(), 0))?;
         let cc = if fields[1].trim() == "" { "ZZ" } else { fields[1] };
         let type_  = fields[2];
+        // NOTE: 部分记录没有日期（空字段或者 00000000）。
+        let date = Date::from_str(fields[5]).ok();
 
-        match type_ {
+        let ip_block = match type_ {
             "ipv4" => {
-                let country_code = Country::from_str(cc)?;
+                let start: Ipv4Addr = fields[3].parse().map_err(|_| invalid(ParseErrorKind::InvalidAddress, 3))?;
+                let nums: u32 = fields[4].parse().map_err(|_| invalid(ParseErrorKind::InvalidValue, 4))?;
+
+                // NOTE: 数量为 0 或者超出 IPv4 地址空间。
+                if nums == 0 || u32::from(start).checked_add(nums - 1).is_none() {
+                    return Err(invalid(ParseErrorKind::InvalidValue, 4));
+                }
+
+                IpBlock::Ipv4Range(Ipv4Range::with_nums(Ipv4Address(start.octets()), nums))
+            }
+            "ipv6" => {
+                let start: Ipv6Addr = fields[3].parse().map_err(|_| invalid(ParseErrorKind::InvalidAddress, 3))?;
+                let prefix_len: u8 = fields[4].parse().map_err(|_| invalid(ParseErrorKind::InvalidValue, 4))?;
 
-                let start: Ipv4Addr = fields[3].parse().map_err(|_| ParseError::Unrecognized)?;
-                let start_ip = Ipv4Address(start.octets());
-                let nums: u32 = fields[4].parse().map_err(|_| ParseError::Unrecognized)?;
+                if prefix_len > 128 {
+                    return Err(invalid(ParseErrorKind::InvalidValue, 4));
+                }
+
+                IpBlock::Ipv6Cidr(Ipv6Cidr::new(Ipv6Address(start.octets()), prefix_len))
+            }
+            _ => {
+                // Not an IPv4 or IPv6 Record Line.
+                // ASN
+                return Err(invalid(ParseErrorKind::Dropped, 2));
+            }
+        };
 
-                assert!(nums > 0);
+        let country_code = Country::from_str(cc).map_err(|e| invalid(e.into(), 1))?;
 
-                let status_ = fields[6];
-                let (status, dst_registry) = if src_registry == Registry::Iana {
-                    let dst_registry = if fields.len() == 7 { fields[6] } else { fields[7] };
-                    (Status::Assigned, Some(Registry::from_str(dst_registry)?))
-                } else {
-                    (Status::from_str(status_)?, None)
-                };
+        let (status, dst_registry, opaque_id) = if src_registry == Registry::Iana {
+            let idx = if fields.len() == 7 { 6 } else { 7 };
+            let dst_registry = Registry::from_str(fields[idx]).map_err(|e| invalid(e.into(), idx))?;
+            (Status::Assigned, Some(dst_registry), None)
+        } else {
+            let status = Status::from_str(fields[6]).map_err(|e| invalid(e.into(), 6))?;
+            // NOTE: 保留地址块的 opaque-id 为空。
+            let opaque_id = fields.get(7).map(|s| s.trim()).filter(|s| !s.is_empty()).map(|s| s.to_string());
+            (status, None, opaque_id)
+        };
+
+        let record = Record {
+            src_registry: src_registry,
+            country: country_code,
+            ip_block: ip_block,
+            status: status,
+            dst_registry: dst_registry,
+            date: date,
+            opaque_id: opaque_id,
+            source: "",
+        };
 
-                let ip_block = IpBlock::Ipv4Range(Ipv4Range::with_nums(start_ip, nums));
+        Ok(record)
+    }
+}
 
-                let record = Record {
-                    src_registry: src_registry,
-                    country: country_code,
-                    ip_block: ip_block,
-                    status: status,
-                    dst_registry: dst_registry
-                };
 
-                Ok(record)
-            }
-            "ipv6" => {
-                let country_code = Country::from_str(cc)?;
 
-                let start: Ipv6Addr = fields[3].parse().map_err(|_| ParseError::Unrecognized)?;
-                let start_ip = Ipv6Address(start.octets());
-                let prefix_len: u8 = fields[4].parse().map_err(|_| ParseError::Unrecognized)?;
+fn codegen_v4(row: &Ipv4Row) -> String {
+    let (first, last, cc, flags, date) = *row;
+    format!("({}, {}, {}, {}, {})", first, last, cc, flags, date)
+}
+
+fn codegen_v6(row: &Ipv6Row) -> String {
+    let (first, last, cc, flags, date) = *row;
+    format!("({}, {}, {}, {}, {})", first, last, cc, flags, date)
+}
+
+fn codegen_asn(row: &AsnRow) -> String {
+    let (first, last, cc, flags, date) = *row;
+    format!("({}, {}, {}, {}, {})", first, last, cc, flags, date)
+}
+
+fn codegen_c_v4(row: &Ipv4Row) -> String {
+    let (first, last, cc, flags, date) = *row;
+    format!("{{ {}, {}, {}, {}, {} }}", first, last, cc, flags, date)
+}
+
+fn codegen_c_v6(row: &Ipv6Row) -> String {
+    // ((__uint128_t)0x75f17d6b3588f843 << 64) | 0xb13dea7c9c324e51
+    let u128_c = |n: u128| format!("U128({}U, {}U)", (n >> 64) as u64, n as u64);
+
+    let (first, last, cc, flags, date) = *row;
+    format!("{{ {}, {}, {}, {}, {} }}", u128_c(first), u128_c(last), cc, flags, date)
+}
+
+
+fn report_error(e: ParseError, lenient: bool, report: &mut ParseReport) -> Result<(), ParseError> {
+    if !lenient && e.is_fatal() {
+        return Err(e);
+    }
+    report.errors.push(e);
+
+    Ok(())
+}
+
+/// Everything read from the delegated files.
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub records: Vec<Record>,
+    pub asn_records: Vec<AsnRecord>,
+    /// Version line of each file that has a valid one.
+    pub headers: Vec<(&'static str, FileHeader)>,
+    pub report: ParseReport,
+}
 
-                assert!(prefix_len <= 128);
+/// Parse one delegated file into `parsed`.
+///
+/// Lines that fail to parse are appended to `parsed.report`. Without `lenient`
+/// the first fatal one (see `ParseError::is_fatal`) is returned instead.
+///
+/// The record counts of the version line and of every summary line are checked
+/// against the record lines of the file, a mismatch usually means a truncated
+/// download.
+fn parse_file(filename: &'static str, content: &str, lenient: bool, parsed: &mut Parsed) -> Result<(), ParseError> {
+    let report = &mut parsed.report;
+    // (line number, version line)
+    let mut header: Option<(usize, Option<FileHeader>)> = None;
+    let mut summaries: Vec<(usize, Summary)> = Vec::new();
+    // type => number of record lines
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (line_idx, line) in content.lines().enumerate() {
+        let line_no = line_idx + 1;
+
+        if line.starts_with("#") || line.trim().is_empty() {
+            // Ignore comment line
+            continue;
+        }
 
-                let status_ = fields[6];
-                let (status, dst_registry) = if src_registry == Registry::Iana {
-                    let dst_registry = if fields.len() == 7 { fields[6] } else { fields[7] };
-                    (Status::Assigned, Some(Registry::from_str(dst_registry)?))
-                } else {
-                    (Status::from_str(status_)?, None)
-                };
+        if header.is_none() {
+            match FileHeader::from_str(line) {
+                Ok(file_header) => header = Some((line_no, Some(file_header))),
+                Err(e) => {
+                    header = Some((line_no, None));
+                    report_error(e.at(filename, line_no), lenient, report)?;
+                }
+            }
+            continue;
+        }
 
-                let ip_block = IpBlock::Ipv6Cidr(Ipv6Cidr::new(start_ip, prefix_len));
+        if line.ends_with("summary") {
+            match Summary::from_str(line) {
+                Ok(summary) => summaries.push((line_no, summary)),
+                Err(e) => report_error(e.at(filename, line_no), lenient, report)?,
+            }
+            continue;
+        }
 
-                let record = Record {
-                    src_registry: src_registry,
-                    country: country_code,
-                    ip_block: ip_block,
-                    status: status,
-                    dst_registry: dst_registry
-                };
+        let type_ = line.split("|").nth(2).unwrap_or("");
+        *counts.entry(type_).or_insert(0) += 1;
 
-                Ok(record)
+        if type_ == "asn" {
+            match AsnRecord::from_str(line) {
+                Ok(mut record) => {
+                    record.source = filename;
+                    parsed.asn_records.push(record);
+                },
+                Err(e) => report_error(e.at(filename, line_no), lenient, report)?,
             }
-            _ => {
-                // Not an IPv4 or IPv6 Record Line.
-                // ASN
-                Err(ParseError::Dropped)
+            continue;
+        }
+        
+        match Record::from_str(line) {
+            Ok(mut record) => {
+                record.source = filename;
+                parsed.records.push(record);
+            },
+            Err(e) => {
+                let e = e.at(filename, line_no);
+                match e.kind {
+                    ParseErrorKind::Dropped => {
+                        // Unknown record type.
+                    },
+                    _ => report_error(e, lenient, report)?,
+                }
             }
         }
     }
-}
 
+    for (line_no, summary) in summaries.iter() {
+        let found = counts.get(summary.type_.as_str()).cloned().unwrap_or(0);
+        if found != summary.count {
+            let text = format!("{} {} records, found {}", summary.count, summary.type_, found);
+            report_error(ParseError::new(ParseErrorKind::CountMismatch, Some(4), &text).at(filename, *line_no),
+                         lenient, report)?;
+        }
+    }
 
+    match header {
+        Some((line_no, Some(file_header))) => {
+            let found = counts.values().sum::<usize>();
+            if found != file_header.records {
+                let text = format!("{} records, found {}", file_header.records, found);
+                report_error(ParseError::new(ParseErrorKind::CountMismatch, Some(3), &text).at(filename, line_no),
+                             lenient, report)?;
+            }
+            parsed.headers.push((filename, file_header));
+        },
+        Some((_, None)) => { },
+        None => {
+            report_error(ParseError::new(ParseErrorKind::InvalidHeader, None, "").at(filename, 0), lenient, report)?;
+        },
+    }
 
-fn parse(data_path: &PathBuf) -> Result<HashSet<Record>, Box<dyn std::error::Error>> {
-    let mut records: HashSet<Record> = HashSet::new();
+    Ok(())
+}
 
-    let filepaths: Vec<PathBuf> = IANA_RIR_FILES.iter()
-                                                .map(|&(filename, _)| data_path.join(filename) )
-                                                .collect();
+fn parse(data_path: &PathBuf, sources: &Sources, lenient: bool) -> Result<Parsed, Box<dyn std::error::Error>> {
+    let mut parsed = Parsed::default();
+    let files_path = sources::current_path(data_path);
 
-    for filepath in filepaths {
+    for source in sources.enabled() {
+        // NOTE: 记录只保存 &'static str 的文件名，每个文件只泄漏一次。
+        let filename: &'static str = Box::leak(source.name.clone().into_boxed_str());
+        let filepath = files_path.join(filename);
         println!("parse file {:?}", filepath);
 
         if !filepath.exists() || !filepath.is_file() {
@@ -518,56 +467,53 @@ fn parse(data_path: &PathBuf) -> Result<HashSet<Record>, Box<dyn std::error::Err
             file.read_to_string(&mut content)?;
             content
         };
-        
-        let mut line_idx = 0usize;
 
-        for line in file_content.lines() {
-            if line.starts_with("#") {
-                // Ignore comment line
-                continue;
-            }
+        parse_file(filename, &file_content, lenient, &mut parsed)?;
+    }
+
+    Ok(parsed)
+}
 
-            if line_idx == 0 || line.ends_with("summary") {
-                // Ignore summary
-                line_idx += 1;
-                continue;
-            }
-            
-            match Record::from_str(line) {
-                Ok(record) => {
-                    records.insert(record);
-                },
-                Err(e) => {
-                    match e {
-                        ParseError::Dropped => {
-                            // Not an IPv4 or IPv6 Record Line.
-                            // AS record.
-                        },
-                        ParseError::InvalidCountryCode | ParseError::Truncated => {
-                            println!("[ERROR] {}  Line: {:?}", e, line);
-                        },
-                        _ => {
-                            println!("Line: {:?}", line);
-                            return Err(Box::new(e));
-                        }
-                    }
-                }
-            }
 
-            line_idx += 1;
+fn resolve<R: Delegation>(records: Vec<R>, resolution: Resolution, name: &str) -> Vec<R> {
+    match validate::validate(records, resolution) {
+        Ok((records, report)) => {
+            report.print(name);
+            records
+        },
+        Err(report) => {
+            report.print(name);
+            println!("[ERROR] overlapping delegations, see above. (--on-conflict {})", resolution);
+            std::process::exit(1);
         }
     }
-
-    Ok(records)
 }
 
+pub struct Config {
+    pub data_path: PathBuf,
+    /// Directory of the generated tables, `src` of the library unless
+    /// `--date` is given, then the snapshot directory.
+    pub output_path: PathBuf,
+    /// Day of the snapshot in the data path, `--date`.
+    pub date: Option<Date>,
+    pub sources: Sources,
+    pub merge: MergePolicy,
+    pub resolution: Resolution,
+    pub lenient: bool,
+}
 
-fn boot() -> PathBuf {
+fn boot() -> Config {
     let args: Vec<String> = std::env::args().collect();
     let program = args[0].clone();
 
     let mut opts = getopts::Options::new();
     opts.optopt("o", "data-path", "Specify the default data path", "");
+    opts.optopt("s", "sources", "Load the source list from a TOML file instead of the builtin one", "FILE");
+    opts.optopt("m", "merge", "Merge adjacent ranges before codegen: country (default), strict, split", "POLICY");
+    opts.optflag("", "no-merge", "Keep one table entry per RIR record, same as `--merge split`");
+    opts.optopt("r", "on-conflict", "Resolve overlapping delegations: prefer-extended (default), prefer-latest, fail", "POLICY");
+    opts.optflag("", "lenient", "Skip malformed lines and report them all at the end");
+    opts.optopt("", "date", "Build from the snapshot of this day in <data-path>/snapshots/YYYYMMDD, the tables are written there too", "YYYYMMDD");
     opts.optflag("h", "help", "print this help menu");
 
     let matches = match opts.parse(&args[1..]) {
@@ -583,33 +529,144 @@ fn boot() -> PathBuf {
     }
 
     let value = matches.opt_str("o").unwrap_or("data".to_string());
+    let mut data_path = Path::new(value.to_lowercase().as_str()).to_path_buf();
+    let mut output_path = Path::new("src").to_path_buf();
+    let mut date = None;
+
+    if let Some(value) = matches.opt_str("date") {
+        match value.parse::<Date>() {
+            Ok(snapshot_date) => {
+                // NOTE: 历史快照不能覆盖库里内置的当前数据表。
+                data_path = sources::snapshot_path(&data_path, &snapshot_date.to_string());
+                output_path = data_path.clone();
+                date = Some(snapshot_date);
+            },
+            Err(e) => {
+                println!("[ERROR] --date: {}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    let merge = if matches.opt_present("no-merge") {
+        MergePolicy::Split
+    } else {
+        match matches.opt_str("m").map(|s| s.parse::<MergePolicy>()) {
+            Some(Ok(merge)) => merge,
+            Some(Err(e)) => {
+                println!("[ERROR] {}", e);
+                std::process::exit(1);
+            },
+            None => MergePolicy::Country,
+        }
+    };
 
-    Path::new(value.to_lowercase().as_str()).to_path_buf()
+    let resolution = match matches.opt_str("r").map(|s| s.parse::<Resolution>()) {
+        Some(Ok(resolution)) => resolution,
+        Some(Err(e)) => {
+            println!("[ERROR] {}", e);
+            std::process::exit(1);
+        },
+        None => Resolution::PreferExtended,
+    };
+
+    let lenient = matches.opt_present("lenient");
+
+    let sources = match Sources::load_or_builtin(matches.opt_str("s")) {
+        Ok(sources) => sources,
+        Err(e) => {
+            println!("[ERROR] --sources: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    Config { data_path, output_path, date, sources, merge, resolution, lenient }
 }
 
 fn main () -> Result<(), Box<dyn std::error::Error>> {
-    let data_path = boot();
+    let config = boot();
+    let data_path = config.data_path.clone();
     
     if !data_path.exists() {
         println!("[ERROR] data path not exists.");
         std::process::exit(1);
     }
 
-    let record_sets = parse(&data_path)?;
+    // NOTE: 快照缺少某个 RIR 的文件时，生成的表里没有这个 RIR 的地址，不能悄悄跳过。
+    if let Some(date) = config.date {
+        let date = date.to_string();
+        let files_path = sources::current_path(&data_path);
+        let (dated, undated): (Vec<&Source>, Vec<&Source>) = config.sources.enabled().partition(|source| source.dated(&date).is_some());
+
+        let missing = dated.iter()
+            .filter(|source| !files_path.join(&source.name).is_file())
+            .map(|source| source.name.as_str())
+            .collect::<Vec<&str>>();
+        if !missing.is_empty() {
+            println!("[ERROR] snapshot {} is incomplete, missing {}. (sync --date {})", date, missing.join(", "), date);
+            std::process::exit(1);
+        }
+        for source in undated.iter().filter(|source| !files_path.join(&source.name).is_file()) {
+            println!("[WARN] {} has no dated files, the snapshot of {} is built without it", source.name, date);
+        }
+    }
 
-    let mut v4_records: Vec<&Record> = record_sets.iter().filter(|record| {
+    let parsed = match parse(&data_path, &config.sources, config.lenient) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            println!("[ERROR] {}", e);
+            std::process::exit(1);
+        }
+    };
+    parsed.report.print();
+
+    let record_sets = &parsed.records;
+    let headers = &parsed.headers;
+
+    for &(filename, ref header) in headers.iter() {
+        println!("source {}: {}", filename, header);
+    }
+    // NOTE: 以最新的文件结束日期作为整个数据库的日期。
+    let data_date_number = headers.iter()
+        .filter_map(|(_, header)| header.end_date)
+        .max()
+        .map(|date| date.number())
+        .unwrap_or(0);
+    let sources = headers.iter()
+        .map(|&(filename, ref header)| format!("//     {}: {}\n", filename, header))
+        .collect::<String>();
+    let data_serials = headers.iter()
+        .map(|&(filename, ref header)| format!("({:?}, {})", filename, header.serial))
+        .collect::<Vec<String>>();
+    let data_serials = format!("#[doc(hidden)]\npub static DATA_SERIALS: [(&str, u64); {}] = [{}];\n\n",
+                               data_serials.len(), data_serials.join(", "));
+
+    let v4_records: Vec<Record> = record_sets.iter().filter(|record| {
         record.is_ipv4() && record.src_registry() != Registry::Iana
-    } ).collect();
-    let mut v6_records: Vec<&Record> = record_sets.iter().filter(|record| {
+    } ).cloned().collect();
+    let v6_records: Vec<Record> = record_sets.iter().filter(|record| {
         record.is_ipv6() && record.src_registry() != Registry::Iana
-    } ).collect();
+    } ).cloned().collect();
+
+    let mut v4_records = resolve(v4_records, config.resolution, "ipv4");
+    let mut v6_records = resolve(v6_records, config.resolution, "ipv6");
 
-    let mut iana_v4_records: Vec<&Record> = record_sets.iter().filter(|record| {
+    let asn_records: Vec<AsnRecord> = parsed.asn_records.iter().filter(|record| {
+        record.src_registry != Registry::Iana
+    } ).cloned().collect();
+    // NOTE: validate 的结果已经按起始 AS 号排序。
+    let asn_records = resolve(asn_records, config.resolution, "asn");
+
+    let iana_v4_records: Vec<Record> = record_sets.iter().filter(|record| {
         record.is_ipv4() && record.dst_registry().is_some() && record.src_registry() == Registry::Iana
-    } ).collect();
-    let mut iana_v6_records: Vec<&Record> = record_sets.iter().filter(|record| {
+    } ).cloned().collect();
+    let iana_v6_records: Vec<Record> = record_sets.iter().filter(|record| {
         record.is_ipv6() && record.dst_registry().is_some() && record.src_registry() == Registry::Iana
-    } ).collect();
+    } ).cloned().collect();
+
+    // NOTE: delegated-iana-latest 和 delegated-iana-extended-latest 的记录重复。
+    let mut iana_v4_records = resolve(iana_v4_records, config.resolution, "iana ipv4");
+    let mut iana_v6_records = resolve(iana_v6_records, config.resolution, "iana ipv6");
 
     v4_records.sort_unstable();
     v6_records.sort_unstable();
@@ -621,8 +678,8 @@ fn main () -> Result<(), Box<dyn std::error::Error>> {
     let iana_v4_output_filepath = data_path.join("iana_v4_records");
     let iana_v6_output_filepath = data_path.join("iana_v6_records");
 
-    let v4_db_filepath = "src/v4_db.rs";
-    let v6_db_filepath = "src/v6_db.rs";
+    let v4_db_filepath = config.output_path.join("v4_db.rs");
+    let v6_db_filepath = config.output_path.join("v6_db.rs");
 
     let _ = fs::remove_file(&v4_output_filepath);
     let _ = fs::remove_file(&v6_output_filepath);
@@ -647,35 +704,77 @@ fn main () -> Result<(), Box<dyn std::error::Error>> {
                         .open(&v6_db_filepath)?;
 
     for record in v4_records.iter() {
-        v4_output_file.write(format!("{}\n", record).as_bytes())?;
+        v4_output_file.write_all(format!("{}\n", record).as_bytes())?;
     }
     for record in v6_records.iter() {
-        v6_output_file.write(format!("{}\n", record).as_bytes())?;
+        v6_output_file.write_all(format!("{}\n", record).as_bytes())?;
     }
 
     for record in iana_v4_records.iter() {
-        iana_v4_output_file.write(format!("{}\n", record).as_bytes())?;
+        iana_v4_output_file.write_all(format!("{}\n", record).as_bytes())?;
     }
     for record in iana_v6_records.iter() {
-        iana_v6_output_file.write(format!("{}\n", record).as_bytes())?;
+        iana_v6_output_file.write_all(format!("{}\n", record).as_bytes())?;
     }
 
-    let v4_db = v4_records.iter().map(|record| format!("    {}", record.codegen()) ).collect::<Vec<String>>();
-    let v6_db = v6_records.iter().map(|record| format!("    {}", record.codegen()) ).collect::<Vec<String>>();
+    let v4_rows = v4_records.iter().map(|record| record.ipv4_row()).collect::<Vec<Ipv4Row>>();
+    let v6_rows = v6_records.iter().map(|record| record.ipv6_row()).collect::<Vec<Ipv6Row>>();
+    let v4_rows = merge::merge_v4(&v4_rows, config.merge);
+    let v6_rows = merge::merge_v6(&v6_rows, config.merge);
+
+    println!("merge ({}): ipv4 {} => {}, ipv6 {} => {}",
+             config.merge,
+             v4_records.len(), v4_rows.len(),
+             v6_records.len(), v6_rows.len());
+
+    let v4_db = v4_rows.iter().map(|row| format!("    {}", codegen_v4(row)) ).collect::<Vec<String>>();
+    let v6_db = v6_rows.iter().map(|row| format!("    {}", codegen_v6(row)) ).collect::<Vec<String>>();
 
-    v4_db_file.write(b"// Format: (first_ip, last_ip, country_index)\n")?;
-    v4_db_file.write(b"#[doc(hidden)]\n")?;
-    v4_db_file.write(format!("pub static IPV4_RECORDS: [(u32, u32, u8); {}] = [\n{}\n];",
+    v4_db_file.write_all(format!("// Sources:\n{}\n", sources).as_bytes())?;
+    v4_db_file.write_all(format!("#[doc(hidden)]\npub static DATA_DATE: u32 = {};\n\n", data_date_number).as_bytes())?;
+    v4_db_file.write_all(data_serials.as_bytes())?;
+    v4_db_file.write_all(b"// Format: (first_ip, last_ip, country_index, registry_index << 4 | status_index, date)\n")?;
+    v4_db_file.write_all(b"#[doc(hidden)]\n")?;
+    v4_db_file.write_all(format!("pub static IPV4_RECORDS: [(u32, u32, u8, u8, u32); {}] = [\n{}\n];",
                                 v4_db.len(),
                                 v4_db.join(",\n"))
                                     .as_bytes())?;
 
-    v6_db_file.write(b"// Format: (first_ip, last_ip, country_index)\n")?;
-    v6_db_file.write(b"#[doc(hidden)]\n")?;
-    v6_db_file.write(format!("pub static IPV6_RECORDS: [(u128, u128, u8); {}] = [\n{}\n];",
+    v6_db_file.write_all(format!("// Sources:\n{}\n", sources).as_bytes())?;
+    v6_db_file.write_all(format!("#[doc(hidden)]\npub static DATA_DATE: u32 = {};\n\n", data_date_number).as_bytes())?;
+    v6_db_file.write_all(data_serials.as_bytes())?;
+    v6_db_file.write_all(b"// Format: (first_ip, last_ip, country_index, registry_index << 4 | status_index, date)\n")?;
+    v6_db_file.write_all(b"#[doc(hidden)]\n")?;
+    v6_db_file.write_all(format!("pub static IPV6_RECORDS: [(u128, u128, u8, u8, u32); {}] = [\n{}\n];",
                                 v6_db.len(),
                                 v6_db.join(",\n"))
                                     .as_bytes())?;
+    // IANA => RIR delegations
+    let iana_v4_rows = iana_v4_records.iter().map(|record| {
+        let (first, last, _cc, _flags, _date) = record.ipv4_row();
+        (first, last, record.dst_registry().unwrap().index())
+    }).collect::<Vec<(u32, u32, u8)>>();
+    let iana_v6_rows = iana_v6_records.iter().map(|record| {
+        let (first, last, _cc, _flags, _date) = record.ipv6_row();
+        (first, last, record.dst_registry().unwrap().index())
+    }).collect::<Vec<(u128, u128, u8)>>();
+    let iana_v4_db = iana_v4_rows.iter().map(|row| format!("    {:?}", row)).collect::<Vec<String>>();
+    let iana_v6_db = iana_v6_rows.iter().map(|row| format!("    {:?}", row)).collect::<Vec<String>>();
+
+    v4_db_file.write_all(b"\n\n// Format: (first_ip, last_ip, registry_index)\n")?;
+    v4_db_file.write_all(b"#[doc(hidden)]\n")?;
+    v4_db_file.write_all(format!("pub static IANA_IPV4_RECORDS: [(u32, u32, u8); {}] = [\n{}\n];",
+                                iana_v4_db.len(),
+                                iana_v4_db.join(",\n"))
+                                    .as_bytes())?;
+
+    v6_db_file.write_all(b"\n\n// Format: (first_ip, last_ip, registry_index)\n")?;
+    v6_db_file.write_all(b"#[doc(hidden)]\n")?;
+    v6_db_file.write_all(format!("pub static IANA_IPV6_RECORDS: [(u128, u128, u8); {}] = [\n{}\n];",
+                                iana_v6_db.len(),
+                                iana_v6_db.join(",\n"))
+                                    .as_bytes())?;
+
     v4_db_file.write_all(r###"
 
 fn main() {
@@ -688,15 +787,73 @@ fn main() {
     println!("{:?}", IPV6_RECORDS[100]);
 }"###.as_bytes())?;
 
+    // Bogons
+    let is_rir = |registry: Registry| !matches!(registry, Registry::Iana | Registry::Ietf);
+    let iana_delegated = |records: &[Record]| -> Vec<(u128, u128)> {
+        records.iter()
+            .filter(|record| record.dst_registry().map(is_rir).unwrap_or(false))
+            .map(|record| record.range())
+            .collect()
+    };
+    let unallocated = |records: &[Record]| -> Vec<(u128, u128)> {
+        records.iter()
+            .filter(|record| !record.status().is_delegated())
+            .map(|record| record.range())
+            .collect()
+    };
+    let bogons_v4 = bogon::bogons_v4(&iana_delegated(&iana_v4_records), &unallocated(&v4_records));
+    let bogons_v6 = bogon::bogons_v6(&iana_delegated(&iana_v6_records), &unallocated(&v6_records));
+
+    println!("bogons: ipv4 {}, ipv6 {}", bogons_v4.len(), bogons_v6.len());
+
+    fs::write(data_path.join("bogons_v4"), bogons_v4.iter().map(|net| format!("{}\n", net)).collect::<String>())?;
+    fs::write(data_path.join("bogons_v6"), bogons_v6.iter().map(|net| format!("{}\n", net)).collect::<String>())?;
+
+    // ASN
+    let asn_output_filepath = data_path.join("asn_records");
+    let asn_db_filepath = config.output_path.join("asn_db.rs");
+
+    let asn_output = asn_records.iter().map(|record| format!("{}\n", record)).collect::<String>();
+    fs::write(&asn_output_filepath, asn_output)?;
+
+    let asn_rows = asn_records.iter().map(|record| record.row()).collect::<Vec<AsnRow>>();
+    let asn_rows = merge::merge_asn(&asn_rows, config.merge);
+
+    println!("merge ({}): asn {} => {}", config.merge, asn_records.len(), asn_rows.len());
+
+    let asn_db = asn_rows.iter().map(|row| format!("    {}", codegen_asn(row)) ).collect::<Vec<String>>();
+    fs::write(&asn_db_filepath, format!("\
+// Sources:
+{}
+// Format: (first_asn, last_asn, country_index, registry_index << 4 | status_index, date)
+#[doc(hidden)]
+pub static ASN_RECORDS: [(u32, u32, u8, u8, u32); {}] = [
+{}
+];
+
+fn main() {{
+    println!(\"{{:?}}\", ASN_RECORDS.first());
+}}", sources, asn_db.len(), asn_db.join(",\n")))?;
+
+    // Binary database, see `src/format.rs`
+    let db_filepath = config.output_path.join("ip.db");
+    let country_codes = COUNTRY_CODES.iter().map(|&(cc, _)| cc).collect::<Vec<&str>>();
+
+    let _ = fs::remove_file(&db_filepath);
+    let db_file = OpenOptions::new().create(true).write(true).open(&db_filepath)?;
+    format::write(io::BufWriter::new(db_file), data_date_number, &country_codes, &v4_rows, &v6_rows, &iana_v4_rows, &iana_v6_rows)?;
+
     // C codegen
     // cc -std=c17 src/v6_db.c
-    let _ = fs::remove_file("src/v4_db.c");
-    let _ = fs::remove_file("src/v6_db.c");
+    let v4_db_c_filepath = config.output_path.join("v4_db.c");
+    let v6_db_c_filepath = config.output_path.join("v6_db.c");
+    let _ = fs::remove_file(&v4_db_c_filepath);
+    let _ = fs::remove_file(&v6_db_c_filepath);
 
     let mut v4_db_file_c = OpenOptions::new().create(true).write(true).append(true)
-                        .open("src/v4_db.c")?;
+                        .open(&v4_db_c_filepath)?;
     let mut v6_db_file_c = OpenOptions::new().create(true).write(true).append(true)
-                        .open("src/v6_db.c")?;
+                        .open(&v6_db_c_filepath)?;
 
     v4_db_file_c.write_all(b"\
 #include <stdio.h>
@@ -706,6 +863,8 @@ struct ipv4_record {
     uint32_t first_ip;
     uint32_t last_ip;
     uint8_t  country_code;
+    uint8_t  registry_status;
+    uint32_t date;
 };
 
 ")?;
@@ -719,19 +878,21 @@ struct ipv6_record {
     uint128_t first_ip;
     uint128_t last_ip;
     uint8_t  country_code;
+    uint8_t  registry_status;
+    uint32_t date;
 };
 
 // ((__uint128_t)0x75f17d6b3588f843 << 64) | 0xb13dea7c9c324e51
 #define U128(hi, lo) ((__uint128_t)hi << 64) | (__uint64_t)lo
 
 ")?;
-    let v4_db_c = v4_records.iter().map(|record| format!("    {}", record.codegen_c()) ).collect::<Vec<String>>();
-    let v6_db_c = v6_records.iter().map(|record| format!("    {}", record.codegen_c()) ).collect::<Vec<String>>();
-    v4_db_file_c.write(format!("static struct ipv4_record IPV4_RECORDS[{}] = {{\n{}\n}};",
+    let v4_db_c = v4_rows.iter().map(|row| format!("    {}", codegen_c_v4(row)) ).collect::<Vec<String>>();
+    let v6_db_c = v6_rows.iter().map(|row| format!("    {}", codegen_c_v6(row)) ).collect::<Vec<String>>();
+    v4_db_file_c.write_all(format!("static struct ipv4_record IPV4_RECORDS[{}] = {{\n{}\n}};",
                                 v4_db_c.len(),
                                 v4_db_c.join(",\n"))
                                     .as_bytes())?;
-    v6_db_file_c.write(format!("static struct ipv6_record IPV6_RECORDS[{}] = {{\n{}\n}};",
+    v6_db_file_c.write_all(format!("static struct ipv6_record IPV6_RECORDS[{}] = {{\n{}\n}};",
                                 v6_db_c.len(),
                                 v6_db_c.join(",\n"))
                                     .as_bytes())?;
@@ -768,3 +929,92 @@ int main(int argc, char const *argv[]) {
 
     Ok(())
 }
+
+
+#[test]
+fn test_record_parse_errors() {
+    let error = |line: &str| {
+        let e = Record::from_str(line).unwrap_err();
+        (e.kind, e.field, e.text)
+    };
+
+    assert_eq!(error("apnic|CN|ipv4|1.0.1.0|256"), (ParseErrorKind::Truncated, None, "apnic|CN|ipv4|1.0.1.0|256".to_string()));
+    assert_eq!(error("apnix|CN|ipv4|1.0.1.0|256|20110414|allocated"), (ParseErrorKind::InvalidRegistry, Some(0), "apnix".to_string()));
+    assert_eq!(error("apnic|C|ipv4|1.0.1.0|256|20110414|allocated"), (ParseErrorKind::InvalidCountryCode, Some(1), "C".to_string()));
+    assert_eq!(error("apnic|CN|ipv4|1.0.1|256|20110414|allocated"), (ParseErrorKind::InvalidAddress, Some(3), "1.0.1".to_string()));
+    assert_eq!(error("apnic|CN|ipv4|1.0.1.0|0|20110414|allocated"), (ParseErrorKind::InvalidValue, Some(4), "0".to_string()));
+    assert_eq!(error("apnic|CN|ipv4|255.255.255.0|257|20110414|allocated"), (ParseErrorKind::InvalidValue, Some(4), "257".to_string()));
+    assert_eq!(error("apnic|CN|ipv6|2001:250::|129|20110414|allocated"), (ParseErrorKind::InvalidValue, Some(4), "129".to_string()));
+    assert_eq!(error("apnic|CN|ipv4|1.0.1.0|256|20110414|allocatd"), (ParseErrorKind::InvalidStatus, Some(6), "allocatd".to_string()));
+    assert_eq!(error("apnic|CN|asn|173|1|20020801|allocated").0, ParseErrorKind::Dropped);
+}
+
+#[test]
+fn test_record_extended_fields() {
+    let record: Record = "apnic|CN|ipv4|1.0.1.0|256|20110414|allocated|A92E1062".parse().unwrap();
+    assert_eq!(record.date(), Some(Date::new(2011, 4, 14).unwrap()));
+    assert_eq!(record.opaque_id(), Some("A92E1062"));
+    assert_eq!(record.to_string(), "apnic CN ipv4 1.0.1.0 256 allocated none 20110414 A92E1062");
+
+    let record: Record = "arin||ipv4|23.128.0.0|1024||reserved|".parse().unwrap();
+    assert_eq!((record.date(), record.opaque_id()), (None, None));
+    assert_eq!(record.to_string(), "arin ZZ ipv4 23.128.0.0 1024 reserved none none none");
+
+    let record: Record = "iana|ZZ|ipv4|1.0.0.0|16777216|20100101|apnic".parse().unwrap();
+    assert_eq!((record.dst_registry(), record.opaque_id()), (Some(Registry::Apnic), None));
+
+    // delegated-iana-extended-latest
+    let record: Record = "iana|ZZ|ipv4|1.0.0.0|16777216|20100101|allocated|apnic".parse().unwrap();
+    assert_eq!((record.dst_registry(), record.opaque_id()), (Some(Registry::Apnic), None));
+}
+
+#[test]
+fn test_parse_file_lenient() {
+    let content = "\
+2|apnic|20261016|5|19830613|20261015|+1000
+apnic|*|asn|*|1|summary
+apnic|*|ipv4|*|4|summary
+apnic|CN|ipv4|1.0.1.0|256|20110414|allocated
+apnic|CN|ipv4|1.0.2.0|0|20110414|allocated
+apnic|CN|asn|173|1|20020801|allocated
+apnic|XX|ipv4|1.0.3.0|256|20110414|allocated
+apnic|CN|ipv4|1.0.4.0|256|20110414|allocatd
+";
+
+    let e = parse_file("delegated-apnic-latest", content, false, &mut Parsed::default()).unwrap_err();
+    assert_eq!((e.kind, e.file, e.line), (ParseErrorKind::InvalidValue, "delegated-apnic-latest", 5));
+    assert_eq!(e.to_string(), "delegated-apnic-latest:5: InvalidValue (field 4): \"0\"");
+
+    let mut parsed = Parsed::default();
+    parse_file("delegated-apnic-latest", content, true, &mut parsed).unwrap();
+    assert_eq!(parsed.headers.iter().map(|(_, header)| header.serial).collect::<Vec<_>>(), vec![20261016]);
+    assert_eq!(parsed.records.len(), 1);
+    assert_eq!(parsed.records[0].source, "delegated-apnic-latest");
+    assert_eq!(parsed.asn_records.len(), 1);
+    assert_eq!(parsed.asn_records[0].first, 173);
+    assert_eq!(parsed.report.errors.iter().map(|e| (e.kind, e.line)).collect::<Vec<_>>(), vec![
+        (ParseErrorKind::InvalidValue, 5),
+        (ParseErrorKind::InvalidCountryCode, 7),
+        (ParseErrorKind::InvalidStatus, 8),
+    ]);
+}
+
+#[test]
+fn test_parse_file_count_mismatch() {
+    // Truncated download, the last ipv4 record is missing.
+    let content = "\
+2|apnic|20261016|3|19830613|20261015|+1000
+apnic|*|ipv4|*|3|summary
+apnic|CN|ipv4|1.0.1.0|256|20110414|allocated
+apnic|CN|ipv4|1.0.2.0|512|20110414|allocated
+";
+
+    let e = parse_file("delegated-apnic-latest", content, false, &mut Parsed::default()).unwrap_err();
+    assert_eq!((e.kind, e.line, e.field), (ParseErrorKind::CountMismatch, 2, Some(4)));
+    assert_eq!(e.text, "3 ipv4 records, found 2");
+
+    let mut parsed = Parsed::default();
+    parse_file("delegated-apnic-latest", content, true, &mut parsed).unwrap();
+    assert_eq!(parsed.records.len(), 2);
+    assert_eq!(parsed.report.count(ParseErrorKind::CountMismatch), 2);
+}
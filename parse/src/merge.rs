@@ -0,0 +1,187 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::asn::AsnRow;
+use crate::status::Status;
+
+
+// Format: (first_ip, last_ip, country_index, registry_index << 4 | status_index, date)
+pub type Ipv4Row = (u32, u32, u8, u8, u32);
+pub type Ipv6Row = (u128, u128, u8, u8, u32);
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct InvalidMergePolicy;
+
+impl std::error::Error for InvalidMergePolicy { }
+
+impl fmt::Display for InvalidMergePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InvalidMergePolicy")
+    }
+}
+
+
+/// How adjacent or overlapping rows are coalesced before codegen.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep one row per RIR record.
+    Split,
+    /// Merge rows of the same country, the default. Delegated rows are never
+    /// merged with available or reserved ones, and available rows never with
+    /// reserved ones, see `Status::is_delegated`.
+    ///
+    /// `LookupResult::country` stays exact, the other fields become
+    /// approximate: `first`, `last` and `network` are the bounds of the merged
+    /// block, `registry` and `status` those of its first row (e.g. `Assigned`
+    /// for a block that is partly `Allocated`), `date` is `None` unless every
+    /// row has the same date.
+    Country,
+    /// Merge rows of the same country, registry, status and date, lookups
+    /// report the same country, registry, status and date as with `Split`.
+    Strict,
+}
+
+impl FromStr for MergePolicy {
+    type Err = InvalidMergePolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "split"   => Ok(MergePolicy::Split),
+            "country" => Ok(MergePolicy::Country),
+            "strict"  => Ok(MergePolicy::Strict),
+                    _ => Err(InvalidMergePolicy),
+        }
+    }
+}
+
+impl fmt::Display for MergePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergePolicy::Split   => write!(f, "split"),
+            MergePolicy::Country => write!(f, "country"),
+            MergePolicy::Strict  => write!(f, "strict"),
+        }
+    }
+}
+
+
+// flags: registry_index << 4 | status_index
+fn is_delegated(flags: u8) -> bool {
+    Status::from_index(flags & 0x0f).map(|status| status.is_delegated()).unwrap_or(false)
+}
+
+// (country_index, flags, date)
+fn mergeable(policy: MergePolicy, a: (u8, u8, u32), b: (u8, u8, u32)) -> bool {
+    match policy {
+        MergePolicy::Split => false,
+        MergePolicy::Country => {
+            let same_status = if is_delegated(a.1) { is_delegated(b.1) } else { a.1 & 0x0f == b.1 & 0x0f };
+            a.0 == b.0 && same_status
+        },
+        MergePolicy::Strict => a == b,
+    }
+}
+
+macro_rules! merge_rows {
+    ($name:ident, $row:ty) => {
+        /// `rows` must be sorted by `first_ip`.
+        ///
+        /// A merged row keeps the date only if every row in it has the same date.
+        pub fn $name(rows: &[$row], policy: MergePolicy) -> Vec<$row> {
+            let mut merged: Vec<$row> = Vec::with_capacity(rows.len());
+
+            for &row in rows.iter() {
+                if let Some(prev) = merged.last_mut() {
+                    let touches = match prev.1.checked_add(1) {
+                        Some(next) => row.0 <= next,
+                        None => true,
+                    };
+
+                    if touches && mergeable(policy, (prev.2, prev.3, prev.4), (row.2, row.3, row.4)) {
+                        if row.1 > prev.1 {
+                            prev.1 = row.1;
+                        }
+                        if prev.4 != row.4 {
+                            prev.4 = 0;
+                        }
+                        continue;
+                    }
+                }
+
+                merged.push(row);
+            }
+
+            merged
+        }
+    };
+}
+
+merge_rows!(merge_v4, Ipv4Row);
+merge_rows!(merge_v6, Ipv6Row);
+merge_rows!(merge_asn, AsnRow);
+
+
+#[test]
+fn test_merge_v4() {
+    let rows = [
+        (0x0100_0100, 0x0100_01ff, 47, 0x10, 20110414),
+        (0x0100_0200, 0x0100_03ff, 47, 0x10, 20110414),
+        // Overlapping
+        (0x0100_0300, 0x0100_04ff, 47, 0x11, 20110415),
+        (0x0100_0500, 0x0100_05ff, 12, 0x11, 20110811),
+        // Not adjacent
+        (0x0100_0700, 0x0100_07ff, 12, 0x11, 20110811),
+    ];
+
+    assert_eq!(merge_v4(&rows, MergePolicy::Split), rows.to_vec());
+    assert_eq!(merge_v4(&rows, MergePolicy::Country), vec![
+        (0x0100_0100, 0x0100_04ff, 47, 0x10, 0),
+        (0x0100_0500, 0x0100_05ff, 12, 0x11, 20110811),
+        (0x0100_0700, 0x0100_07ff, 12, 0x11, 20110811),
+    ]);
+    assert_eq!(merge_v4(&rows, MergePolicy::Strict), vec![
+        (0x0100_0100, 0x0100_03ff, 47, 0x10, 20110414),
+        (0x0100_0300, 0x0100_04ff, 47, 0x11, 20110415),
+        (0x0100_0500, 0x0100_05ff, 12, 0x11, 20110811),
+        (0x0100_0700, 0x0100_07ff, 12, 0x11, 20110811),
+    ]);
+}
+
+#[test]
+fn test_merge_v4_delegated() {
+    // ZZ, allocated then reserved then available
+    let rows = [
+        (0x0100_0000, 0x0100_00ff, 0, 0x20, 0),
+        (0x0100_0100, 0x0100_01ff, 0, 0x23, 0),
+        (0x0100_0200, 0x0100_02ff, 0, 0x22, 0),
+    ];
+
+    assert_eq!(merge_v4(&rows, MergePolicy::Country), rows.to_vec());
+    assert_eq!(merge_v4(&rows, MergePolicy::Strict), rows.to_vec());
+}
+
+#[test]
+fn test_merge_v4_strict_date() {
+    let rows = [
+        (0x0100_0000, 0x0100_00ff, 47, 0x10, 20110414),
+        (0x0100_0100, 0x0100_01ff, 47, 0x10, 20110414),
+        (0x0100_0200, 0x0100_02ff, 47, 0x10, 20110415),
+    ];
+
+    assert_eq!(merge_v4(&rows, MergePolicy::Strict), vec![
+        (0x0100_0000, 0x0100_01ff, 47, 0x10, 20110414),
+        (0x0100_0200, 0x0100_02ff, 47, 0x10, 20110415),
+    ]);
+    assert_eq!(merge_v4(&rows, MergePolicy::Country), vec![(0x0100_0000, 0x0100_02ff, 47, 0x10, 0)]);
+}
+
+#[test]
+fn test_merge_v6_end_of_space() {
+    let rows = [
+        (u128::MAX - 0xff, u128::MAX, 47, 0x10, 0),
+        (u128::MAX - 0x0f, u128::MAX, 47, 0x10, 0),
+    ];
+
+    assert_eq!(merge_v6(&rows, MergePolicy::Strict), vec![(u128::MAX - 0xff, u128::MAX, 47, 0x10, 0)]);
+}
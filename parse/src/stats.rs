@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::date::Date;
+use crate::registry::Registry;
+use crate::error::{ParseError, ParseErrorKind};
+
+
+// 4.1 Version line
+// https://www.apnic.net/about-apnic/corporate-documents/documents/resource-guidelines/rir-statistics-exchange-format/#FileHeader
+//
+// Format:
+//
+//      version | registry | serial | records | startdate | enddate | UTCoffset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHeader {
+    /// Format version, e.g. `2` or `2.3`.
+    pub version: String,
+    pub registry: Registry,
+    /// Serial number of this file within the registry.
+    pub serial: u64,
+    /// Number of records, excluding the version line, summary lines and comments.
+    pub records: usize,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    /// Offset from UTC in minutes.
+    pub utc_offset: i32,
+}
+
+impl FromStr for FileHeader {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split("|").collect();
+        let invalid = |idx: usize| ParseError::new(ParseErrorKind::InvalidHeader, Some(idx), fields[idx]);
+
+        if fields.len() < 7 {
+            return Err(ParseError::new(ParseErrorKind::InvalidHeader, None, s));
+        }
+
+        let version = fields[0];
+        if version.is_empty() || !version.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(invalid(0));
+        }
+
+        Ok(FileHeader {
+            version: version.to_string(),
+            registry: fields[1].parse().map_err(|_| invalid(1))?,
+            serial: fields[2].parse().map_err(|_| invalid(2))?,
+            records: fields[3].parse().map_err(|_| invalid(3))?,
+            // NOTE: ARIN 的起始日期是 19700101，也有文件留空或写 00000000。
+            start_date: Date::from_str(fields[4]).ok(),
+            end_date: Date::from_str(fields[5]).ok(),
+            utc_offset: parse_utc_offset(fields[6]).ok_or_else(|| invalid(6))?,
+        })
+    }
+}
+
+impl fmt::Display for FileHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let date = |date: Option<Date>| date.map(|date| format!("{:?}", date)).unwrap_or("-".to_string());
+        let (sign, offset) = if self.utc_offset < 0 { ('-', -self.utc_offset) } else { ('+', self.utc_offset) };
+
+        write!(f, "{} serial {}, {} records, {} - {} ({}{:02}{:02})",
+               self.registry,
+               self.serial,
+               self.records,
+               date(self.start_date),
+               date(self.end_date),
+               sign, offset / 60, offset % 60)
+    }
+}
+
+// `+hhmm` or `-hhmm`
+fn parse_utc_offset(s: &str) -> Option<i32> {
+    // NOTE: 先检查 ASCII，否则按字节切片可能落在多字节字符中间。
+    if s.len() != 5 || !s.is_ascii() || !s[1..].bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let hours: i32 = s[1..3].parse().ok()?;
+    let minutes: i32 = s[3..5].parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+
+    match &s[..1] {
+        "+" => Some(hours * 60 + minutes),
+        "-" => Some(-(hours * 60 + minutes)),
+          _ => None,
+    }
+}
+
+
+// 4.2 Summary line
+//
+// Format:
+//
+//      registry | * | type | * | count | summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub registry: Registry,
+    /// `asn`, `ipv4` or `ipv6`.
+    pub type_: String,
+    pub count: usize,
+}
+
+impl FromStr for Summary {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split("|").collect();
+        let invalid = |idx: usize| ParseError::new(ParseErrorKind::InvalidSummary, Some(idx), fields[idx]);
+
+        if fields.len() != 6 || fields[5] != "summary" {
+            return Err(ParseError::new(ParseErrorKind::InvalidSummary, None, s));
+        }
+
+        Ok(Summary {
+            registry: fields[0].parse().map_err(|_| invalid(0))?,
+            type_: fields[2].to_string(),
+            count: fields[4].parse().map_err(|_| invalid(4))?,
+        })
+    }
+}
+
+
+#[test]
+fn test_file_header() {
+    let header: FileHeader = "2.3|arin|1760000000|5|19700101|20261015|-0400".parse().unwrap();
+    assert_eq!(header.version, "2.3");
+    assert_eq!(header.registry, Registry::Arin);
+    assert_eq!(header.serial, 1760000000);
+    assert_eq!(header.records, 5);
+    assert_eq!(header.end_date, Some(Date::new(2026, 10, 15).unwrap()));
+    assert_eq!(header.utc_offset, -240);
+    assert_eq!(header.to_string(), "arin serial 1760000000, 5 records, 1970-01-01 - 2026-10-15 (-0400)");
+
+    let header: FileHeader = "2|apnic|20261016|7|00000000|20261015|+1000".parse().unwrap();
+    assert_eq!((header.start_date, header.utc_offset), (None, 600));
+
+    let e = "2|apnic|20261016|seven|19830613|20261015|+1000".parse::<FileHeader>().unwrap_err();
+    assert_eq!((e.kind, e.field), (ParseErrorKind::InvalidHeader, Some(3)));
+    let e = "apnic|CN|ipv4|1.0.1.0|256|20110414|allocated".parse::<FileHeader>().unwrap_err();
+    assert_eq!((e.kind, e.field), (ParseErrorKind::InvalidHeader, Some(0)));
+}
+
+#[test]
+fn test_utc_offset() {
+    assert_eq!(parse_utc_offset("+0530"), Some(330));
+    assert_eq!(parse_utc_offset("-0000"), Some(0));
+    assert_eq!(parse_utc_offset("+0560"), None);
+    assert_eq!(parse_utc_offset("0000"), None);
+    // 5 bytes, but not ASCII
+    assert_eq!(parse_utc_offset("é000"), None);
+    assert_eq!(parse_utc_offset("+é00"), None);
+
+    let e = "2|apnic|20261016|7|19830613|20261015|é000".parse::<FileHeader>().unwrap_err();
+    assert_eq!((e.kind, e.field), (ParseErrorKind::InvalidHeader, Some(6)));
+}
+
+#[test]
+fn test_summary() {
+    let summary: Summary = "lacnic|*|ipv4|*|150|summary".parse().unwrap();
+    assert_eq!(summary, Summary { registry: Registry::Lacnic, type_: "ipv4".to_string(), count: 150 });
+
+    let e = "lacnic|*|ipv4|*|many|summary".parse::<Summary>().unwrap_err();
+    assert_eq!((e.kind, e.field), (ParseErrorKind::InvalidSummary, Some(4)));
+}
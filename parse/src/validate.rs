@@ -0,0 +1,380 @@
+use smoltcp::wire::{Ipv4Address, Ipv6Address, Ipv6Cidr};
+
+use std::fmt;
+use std::cmp;
+use std::str::FromStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::Record;
+use crate::net::IpNetIter;
+use crate::block::{IpBlock, Ipv4Range};
+use crate::date::Date;
+use crate::country::Country;
+use crate::registry::Registry;
+use crate::status::Status;
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct InvalidResolution;
+
+impl std::error::Error for InvalidResolution { }
+
+impl fmt::Display for InvalidResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InvalidResolution")
+    }
+}
+
+
+/// Which record wins when delegations overlap.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Resolution {
+    /// `*-extended-latest` over `*-latest`, then the later date.
+    PreferExtended,
+    /// The later date, then `*-extended-latest` over `*-latest`.
+    PreferLatest,
+    /// Abort on any overlap or conflict.
+    Fail,
+}
+
+impl FromStr for Resolution {
+    type Err = InvalidResolution;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefer-extended" => Ok(Resolution::PreferExtended),
+            "prefer-latest"   => Ok(Resolution::PreferLatest),
+            "fail"            => Ok(Resolution::Fail),
+                            _ => Err(InvalidResolution),
+        }
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Resolution::PreferExtended => write!(f, "prefer-extended"),
+            Resolution::PreferLatest   => write!(f, "prefer-latest"),
+            Resolution::Fail           => write!(f, "fail"),
+        }
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The same range with the same attributes, e.g. from `*-latest` and `*-extended-latest`.
+    Duplicate,
+    /// The same range with different country, registry or status.
+    Conflict,
+    /// Different ranges that share addresses, same country.
+    Overlap,
+    /// Different ranges that share addresses, different country.
+    CountryConflict,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IssueKind::Duplicate       => write!(f, "DUPLICATE"),
+            IssueKind::Conflict        => write!(f, "CONFLICT"),
+            IssueKind::Overlap         => write!(f, "OVERLAP"),
+            IssueKind::CountryConflict => write!(f, "COUNTRY CONFLICT"),
+        }
+    }
+}
+
+/// A delegated range of IP addresses or AS numbers.
+pub trait Delegation: Clone + fmt::Display {
+    /// `(first, last)` as numbers.
+    fn range(&self) -> (u128, u128);
+    fn country(&self) -> Country;
+    fn src_registry(&self) -> Registry;
+    fn status(&self) -> Status;
+    fn date(&self) -> Option<Date>;
+    /// Name of the delegated file.
+    fn source(&self) -> &'static str;
+    /// Records with the same attributes that cover exactly `first..=last`, a
+    /// part of this one.
+    fn slice(&self, first: u128, last: u128) -> Vec<Self>;
+}
+
+impl Delegation for Record {
+    fn range(&self) -> (u128, u128) {
+        Record::range(self)
+    }
+
+    fn country(&self) -> Country {
+        self.country
+    }
+
+    fn src_registry(&self) -> Registry {
+        self.src_registry
+    }
+
+    fn status(&self) -> Status {
+        self.status
+    }
+
+    fn date(&self) -> Option<Date> {
+        self.date
+    }
+
+    fn source(&self) -> &'static str {
+        self.source
+    }
+
+    fn slice(&self, first: u128, last: u128) -> Vec<Self> {
+        // NOTE: IPv6 记录只能是 CIDR，切开后可能变成多条。
+        let blocks = if self.is_ipv4() {
+            let range = Ipv4Range::new(Ipv4Address(Ipv4Addr::from(first as u32).octets()),
+                                       Ipv4Address(Ipv4Addr::from(last as u32).octets()));
+            vec![IpBlock::Ipv4Range(range)]
+        } else {
+            IpNetIter::new(IpAddr::from(Ipv6Addr::from(first)), IpAddr::from(Ipv6Addr::from(last)))
+                .map(|net| match net.network() {
+                    IpAddr::V6(v6_addr) => IpBlock::Ipv6Cidr(Ipv6Cidr::new(Ipv6Address(v6_addr.octets()), net.prefix_len())),
+                    IpAddr::V4(_) => unreachable!(),
+                })
+                .collect()
+        };
+
+        blocks.into_iter().map(|ip_block| Record { ip_block, ..self.clone() }).collect()
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Issue<R = Record> {
+    pub kind: IssueKind,
+    pub kept: R,
+    pub dropped: R,
+}
+
+impl<R: Delegation> fmt::Display for Issue<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] kept {} ({}), dropped {} ({})",
+               self.kind,
+               self.kept, self.kept.source(),
+               self.dropped, self.dropped.source())
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Report<R = Record> {
+    pub issues: Vec<Issue<R>>,
+}
+
+impl<R> Default for Report<R> {
+    fn default() -> Self {
+        Report { issues: Vec::new() }
+    }
+}
+
+impl<R: Delegation> Report<R> {
+    pub fn count(&self, kind: IssueKind) -> usize {
+        self.issues.iter().filter(|issue| issue.kind == kind).count()
+    }
+
+    /// Anything other than exact duplicates.
+    pub fn has_conflicts(&self) -> bool {
+        self.issues.iter().any(|issue| issue.kind != IssueKind::Duplicate)
+    }
+
+    /// Print every issue except duplicates, then a summary line.
+    pub fn print(&self, name: &str) {
+        for issue in self.issues.iter().filter(|issue| issue.kind != IssueKind::Duplicate) {
+            println!("{}", issue);
+        }
+
+        println!("validate {}: {} duplicates, {} conflicts, {} overlaps, {} country conflicts",
+                 name,
+                 self.count(IssueKind::Duplicate),
+                 self.count(IssueKind::Conflict),
+                 self.count(IssueKind::Overlap),
+                 self.count(IssueKind::CountryConflict));
+    }
+}
+
+
+fn same_attributes<R: Delegation>(a: &R, b: &R) -> bool {
+    a.country() == b.country() && a.src_registry() == b.src_registry() && a.status() == b.status()
+}
+
+fn is_extended<R: Delegation>(record: &R) -> bool {
+    record.source().contains("-extended-")
+}
+
+// Greater is preferred.
+fn priority<R: Delegation>(resolution: Resolution, a: &R, b: &R) -> cmp::Ordering {
+    match resolution {
+        Resolution::PreferExtended | Resolution::Fail => {
+            is_extended(a).cmp(&is_extended(b)).then(a.date().cmp(&b.date()))
+        },
+        Resolution::PreferLatest => {
+            a.date().cmp(&b.date()).then(is_extended(a).cmp(&is_extended(b)))
+        },
+    }
+}
+
+fn classify<R: Delegation>(a: &R, b: &R) -> IssueKind {
+    if a.range() == b.range() {
+        if same_attributes(a, b) { IssueKind::Duplicate } else { IssueKind::Conflict }
+    } else if a.country() == b.country() {
+        IssueKind::Overlap
+    } else {
+        IssueKind::CountryConflict
+    }
+}
+
+/// Resolve duplicated and overlapping records of one address family (or of AS
+/// numbers), the result is sorted and non-overlapping.
+///
+/// Overlapping records are grouped, then taken from the most preferred one down.
+/// Only the addresses of a record that are already taken are dropped, and
+/// reported, the rest of it is kept.
+pub fn validate<R: Delegation>(mut records: Vec<R>, resolution: Resolution) -> Result<(Vec<R>, Report<R>), Report<R>> {
+    records.sort_by_key(|record| record.range());
+
+    let mut report = Report::default();
+    let mut resolved: Vec<R> = Vec::with_capacity(records.len());
+
+    let mut idx = 0usize;
+    while idx < records.len() {
+        // Overlapping group: records[idx..end]
+        let mut group_last = records[idx].range().1;
+        let mut end = idx + 1;
+        while end < records.len() && records[end].range().0 <= group_last {
+            group_last = cmp::max(group_last, records[end].range().1);
+            end += 1;
+        }
+
+        let mut group = records[idx..end].to_vec();
+        group.sort_by(|a, b| priority(resolution, b, a));
+
+        // (kept part, the record it is a part of)
+        let mut kept: Vec<(R, R)> = Vec::new();
+        for record in group.into_iter() {
+            let (first, last) = record.range();
+
+            // Parts of `record` not taken yet
+            let mut free = vec![(first, last)];
+            let mut taken = Vec::new();
+            for (part, winner) in kept.iter() {
+                let (part_first, part_last) = part.range();
+                if part_last < first || last < part_first {
+                    continue;
+                }
+
+                let contested = (cmp::max(first, part_first), cmp::min(last, part_last));
+                for dropped in record.slice(contested.0, contested.1) {
+                    report.issues.push(Issue { kind: classify(winner, &record), kept: winner.clone(), dropped });
+                }
+                free = free.into_iter().flat_map(|range| subtract(range, contested)).collect();
+            }
+
+            for (free_first, free_last) in free {
+                for part in record.slice(free_first, free_last) {
+                    taken.push((part, record.clone()));
+                }
+            }
+            kept.extend(taken);
+        }
+
+        resolved.extend(kept.into_iter().map(|(part, _)| part));
+        idx = end;
+    }
+
+    if resolution == Resolution::Fail && report.has_conflicts() {
+        return Err(report);
+    }
+
+    resolved.sort_by_key(|record| record.range());
+
+    Ok((resolved, report))
+}
+
+// `range` without the addresses of `other`.
+fn subtract(range: (u128, u128), other: (u128, u128)) -> Vec<(u128, u128)> {
+    if other.1 < range.0 || range.1 < other.0 {
+        return vec![range];
+    }
+
+    let mut ranges = Vec::new();
+    if range.0 < other.0 {
+        ranges.push((range.0, other.0 - 1));
+    }
+    if other.1 < range.1 {
+        ranges.push((other.1 + 1, range.1));
+    }
+
+    ranges
+}
+
+
+#[cfg(test)]
+fn record(line: &str, source: &'static str) -> Record {
+    let mut record: Record = line.parse().unwrap();
+    record.source = source;
+    record
+}
+
+#[test]
+fn test_validate_duplicate() {
+    let records = vec![
+        record("apnic|CN|ipv4|1.0.1.0|256|20110414|allocated", "delegated-apnic-latest"),
+        record("apnic|CN|ipv4|1.0.1.0|256|20110414|allocated|A92E1062", "delegated-apnic-extended-latest"),
+    ];
+
+    let (resolved, report) = validate(records.clone(), Resolution::Fail).unwrap();
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].source, "delegated-apnic-extended-latest");
+    assert_eq!(report.count(IssueKind::Duplicate), 1);
+    assert!(!report.has_conflicts());
+}
+
+#[test]
+fn test_validate_conflicts() {
+    let records = vec![
+        record("apnic|CN|ipv4|1.0.0.0|1024|20110414|allocated", "delegated-apnic-extended-latest"),
+        record("ripencc|NL|ipv4|1.0.2.0|256|20150101|allocated", "delegated-ripencc-latest"),
+        record("apnic|CN|ipv4|1.0.0.0|1024|20110414|assigned", "delegated-apnic-latest"),
+        record("apnic|CN|ipv4|1.0.4.0|256|20110414|allocated", "delegated-apnic-latest"),
+    ];
+
+    assert!(validate(records.clone(), Resolution::Fail).is_err());
+
+    let (resolved, report) = validate(records.clone(), Resolution::PreferExtended).unwrap();
+    assert_eq!(resolved.iter().map(|r| r.country.code()).collect::<Vec<_>>(), vec!["CN", "CN"]);
+    assert_eq!(report.count(IssueKind::Conflict), 1);
+    assert_eq!(report.count(IssueKind::CountryConflict), 1);
+
+    // Only the /24 taken by NL is dropped from CN 1.0.0.0/22.
+    let (resolved, report) = validate(records, Resolution::PreferLatest).unwrap();
+    assert_eq!(resolved.iter().map(|r| (r.country.code(), r.range())).collect::<Vec<_>>(), vec![
+        ("CN", (0x0100_0000, 0x0100_01ff)),
+        ("NL", (0x0100_0200, 0x0100_02ff)),
+        ("CN", (0x0100_0300, 0x0100_03ff)),
+        ("CN", (0x0100_0400, 0x0100_04ff)),
+    ]);
+
+    let country_conflicts = report.issues.iter().filter(|issue| issue.kind == IssueKind::CountryConflict).collect::<Vec<_>>();
+    assert_eq!(country_conflicts.len(), 2);
+    assert!(country_conflicts.iter().all(|issue| issue.dropped.range() == (0x0100_0200, 0x0100_02ff)));
+}
+
+#[test]
+fn test_validate_partial_ipv6() {
+    let records = vec![
+        record("ripencc|NL|ipv6|2001:db8::|32|20150101|allocated", "delegated-ripencc-latest"),
+        record("ripencc|DE|ipv6|2001:db8:8000::|33|20200101|allocated", "delegated-ripencc-latest"),
+    ];
+
+    let (resolved, report) = validate(records, Resolution::PreferLatest).unwrap();
+    assert_eq!(resolved.iter().map(|r| (r.country.code(), r.ip_block.to_string())).collect::<Vec<_>>(), vec![
+        ("NL", "2001:db8::/33".to_string()),
+        ("DE", "2001:db8:8000::/33".to_string()),
+    ]);
+    assert_eq!(report.count(IssueKind::CountryConflict), 1);
+}
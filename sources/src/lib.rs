@@ -0,0 +1,324 @@
+// Source list shared by `sync` and `parse`.
+extern crate serde;
+extern crate toml;
+
+use serde::Deserialize;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+
+// Files not exists
+// ("delegated-arin-latest",             "https://ftp.arin.net/pub/stats/arin/delegated-arin-latest"),
+pub const IANA_RIR_FILES: [(&str, &str); 11] = [
+    ("delegated-arin-extended-latest",    "https://ftp.arin.net/pub/stats/arin/delegated-arin-extended-latest"),
+    ("delegated-ripencc-latest",          "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-latest"),
+    ("delegated-ripencc-extended-latest", "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-extended-latest"),
+    ("delegated-apnic-latest",            "https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"),
+    ("delegated-apnic-extended-latest",   "https://ftp.apnic.net/stats/apnic/delegated-apnic-extended-latest"),
+    ("delegated-lacnic-latest",           "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-latest"),
+    ("delegated-lacnic-extended-latest",  "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-extended-latest"),
+    ("delegated-afrinic-latest",          "https://ftp.afrinic.net/pub/stats/afrinic/delegated-afrinic-latest"),
+    ("delegated-afrinic-extended-latest", "https://ftp.afrinic.net/pub/stats/afrinic/delegated-afrinic-extended-latest"),
+    ("delegated-iana-latest",             "https://ftp.apnic.net/stats/iana/delegated-iana-latest"),
+    // FTP only
+    ("delegated-iana-extended-latest",    "ftp://ftp.apnic.net/public/stats/iana/delegated-iana-extended-latest"),
+];
+
+
+#[derive(Debug)]
+pub enum SourcesError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    /// Two sources with the same name.
+    Duplicate(String),
+    Empty,
+}
+
+impl From<std::io::Error> for SourcesError {
+    fn from(e: std::io::Error) -> Self {
+        SourcesError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for SourcesError {
+    fn from(e: toml::de::Error) -> Self {
+        SourcesError::Toml(e)
+    }
+}
+
+impl std::error::Error for SourcesError { }
+
+impl fmt::Display for SourcesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourcesError::Io(e) => write!(f, "{}", e),
+            SourcesError::Toml(e) => write!(f, "{}", e),
+            SourcesError::Duplicate(name) => write!(f, "duplicate source {:?}", name),
+            SourcesError::Empty => write!(f, "no source"),
+        }
+    }
+}
+
+
+/// How a source is verified.
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumFormat {
+    /// A `.md5` file, in any of the formats the RIRs publish.
+    #[default]
+    Md5,
+    None,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn dated_files() -> bool {
+    true
+}
+
+/// A delegated file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Source {
+    /// File name in the data path, e.g. `delegated-apnic-latest`.
+    pub name: String,
+    pub url: String,
+    /// Tried in order when `url` fails.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    #[serde(default)]
+    pub checksum: ChecksumFormat,
+    /// Defaults to the download URL with `.md5` appended.
+    pub checksum_url: Option<String>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// URL of the file of one day, `{date}` is replaced by `YYYYMMDD` and
+    /// `{year}` by `YYYY`. Defaults to the URLs with `latest` replaced by the
+    /// date. `.gz` and `.bz2` files are decompressed by `sync`.
+    pub dated_url: Option<String>,
+    /// `false` if the source publishes no dated files,
+    /// `sync --date/--since` leaves it out of the snapshots.
+    #[serde(default = "dated_files")]
+    pub dated_files: bool,
+}
+
+// `delegated-apnic-extended-latest` => `delegated-apnic-extended-20260101`
+fn replace_latest(url: &str, date: &str) -> Option<String> {
+    let idx = url.rfind("latest")?;
+
+    Some(format!("{}{}{}", &url[..idx], date, &url[idx + "latest".len()..]))
+}
+
+impl Source {
+    /// `url`, then the mirrors.
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(|url| url.as_str()))
+    }
+
+    /// Checksum URL to use with the download URL `url`, `None` if the source
+    /// is not verified.
+    pub fn checksum_url(&self, url: &str) -> Option<String> {
+        match self.checksum {
+            ChecksumFormat::None => None,
+            ChecksumFormat::Md5 => match self.checksum_url {
+                Some(ref checksum_url) => Some(checksum_url.clone()),
+                None => Some(format!("{}.md5", url)),
+            },
+        }
+    }
+
+    /// The source of the file published on `date` (`YYYYMMDD`), `None` if
+    /// there is no dated URL.
+    pub fn dated(&self, date: &str) -> Option<Source> {
+        if !self.dated_files {
+            return None;
+        }
+
+        let mut urls = match self.dated_url {
+            Some(ref template) => vec![template.replace("{date}", date).replace("{year}", &date[..4])],
+            None => self.urls().filter_map(|url| replace_latest(url, date)).collect::<Vec<String>>(),
+        };
+        if urls.is_empty() {
+            return None;
+        }
+
+        Some(Source {
+            name: self.name.clone(),
+            url: urls.remove(0),
+            mirrors: urls,
+            checksum: self.checksum,
+            checksum_url: None,
+            enabled: self.enabled,
+            dated_url: None,
+            dated_files: true,
+        })
+    }
+}
+
+
+/// Directory of the snapshot of `date` (`YYYYMMDD`), files keep their
+/// `-latest` names so `parse` reads it like a data path.
+pub fn snapshot_path(data_path: &Path, date: &str) -> PathBuf {
+    data_path.join("snapshots").join(date)
+}
+
+/// Directory of the synced files in `data_path`: the generation the
+/// `current` symlink written by `sync` points to, or `data_path` itself if
+/// there is none, e.g. files copied there by hand.
+pub fn current_path(data_path: &Path) -> PathBuf {
+    let current = data_path.join("current");
+    if current.is_dir() {
+        current
+    } else {
+        data_path.to_path_buf()
+    }
+}
+
+
+/// The source list, see `sources.example.toml`:
+///
+/// ```toml
+/// [[source]]
+/// name = "delegated-apnic-latest"
+/// url = "https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"
+/// mirrors = ["https://mirror.example.net/apnic/delegated-apnic-latest"]
+/// checksum = "md5"
+/// enabled = true
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sources {
+    #[serde(rename = "source")]
+    pub sources: Vec<Source>,
+}
+
+impl Sources {
+    /// The `IANA_RIR_FILES`.
+    pub fn builtin() -> Self {
+        let sources = IANA_RIR_FILES.iter().map(|&(name, url)| {
+            // NOTE: AFRINIC 的历史文件按年份放在子目录里；RIPE NCC 的历史文件只有
+            //       按年份存放的 .bz2 压缩包，IANA 没有历史文件。
+            let dated_url = match name {
+                "delegated-afrinic-latest" | "delegated-afrinic-extended-latest" => {
+                    Some(url.replace("/afrinic/", "/afrinic/{year}/").replace("latest", "{date}"))
+                },
+                "delegated-ripencc-latest" | "delegated-ripencc-extended-latest" => {
+                    Some(format!("{}.bz2", url.replace("/ripencc/", "/ripencc/{year}/").replace("latest", "{date}")))
+                },
+                _ => None,
+            };
+            let dated_files = !name.starts_with("delegated-iana-");
+
+            Source {
+                name: name.to_string(),
+                url: url.to_string(),
+                mirrors: Vec::new(),
+                // NOTE: IANA 的文件没有 MD5 校验码。
+                checksum: if name.starts_with("delegated-iana-") { ChecksumFormat::None } else { ChecksumFormat::Md5 },
+                checksum_url: None,
+                enabled: true,
+                dated_url,
+                dated_files,
+            }
+        }).collect();
+
+        Sources { sources }
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self, SourcesError> {
+        let sources: Sources = toml::from_str(s)?;
+
+        if sources.sources.is_empty() {
+            return Err(SourcesError::Empty);
+        }
+        for (idx, source) in sources.sources.iter().enumerate() {
+            if sources.sources[..idx].iter().any(|other| other.name == source.name) {
+                return Err(SourcesError::Duplicate(source.name.clone()));
+            }
+        }
+
+        Ok(sources)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SourcesError> {
+        Sources::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// `path` if given, otherwise the builtin list.
+    pub fn load_or_builtin<P: AsRef<Path>>(path: Option<P>) -> Result<Self, SourcesError> {
+        match path {
+            Some(path) => Sources::load(path),
+            None => Ok(Sources::builtin()),
+        }
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Source> {
+        self.sources.iter().filter(|source| source.enabled)
+    }
+}
+
+
+#[test]
+fn test_sources() {
+    let sources = Sources::from_toml(r#"
+[[source]]
+name = "delegated-apnic-latest"
+url = "https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"
+mirrors = ["https://mirror.example.net/apnic/delegated-apnic-latest"]
+
+[[source]]
+name = "delegated-iana-latest"
+url = "https://ftp.apnic.net/stats/iana/delegated-iana-latest"
+checksum = "none"
+
+[[source]]
+name = "delegated-lacnic-latest"
+url = "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-latest"
+checksum_url = "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-latest.md5"
+enabled = false
+"#).unwrap();
+
+    assert_eq!(sources.enabled().map(|source| source.name.as_str()).collect::<Vec<&str>>(),
+               vec!["delegated-apnic-latest", "delegated-iana-latest"]);
+
+    let apnic = &sources.sources[0];
+    assert_eq!(apnic.urls().count(), 2);
+    assert_eq!(apnic.checksum_url("https://mirror.example.net/apnic/delegated-apnic-latest").unwrap(),
+               "https://mirror.example.net/apnic/delegated-apnic-latest.md5");
+    assert_eq!(sources.sources[1].checksum_url(&sources.sources[1].url), None);
+    assert_eq!(sources.sources[2].checksum_url("https://mirror.example.net/x").unwrap(),
+               "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-latest.md5");
+
+    let dated = apnic.dated("20260101").unwrap();
+    assert_eq!(dated.name, "delegated-apnic-latest");
+    assert_eq!(dated.urls().collect::<Vec<&str>>(),
+               vec!["https://ftp.apnic.net/stats/apnic/delegated-apnic-20260101",
+                    "https://mirror.example.net/apnic/delegated-apnic-20260101"]);
+    assert_eq!(dated.checksum_url(&dated.url).unwrap(), "https://ftp.apnic.net/stats/apnic/delegated-apnic-20260101.md5");
+
+    let mut lacnic = sources.sources[2].clone();
+    lacnic.dated_url = Some("https://ftp.lacnic.net/pub/stats/lacnic/{year}/delegated-lacnic-{date}".to_string());
+    assert_eq!(lacnic.dated("20250630").unwrap().urls().collect::<Vec<&str>>(),
+               vec!["https://ftp.lacnic.net/pub/stats/lacnic/2025/delegated-lacnic-20250630"]);
+
+    let builtin = Sources::builtin();
+    assert_eq!(builtin.enabled().count(), IANA_RIR_FILES.len());
+    assert_eq!(builtin.sources.last().unwrap().checksum, ChecksumFormat::None);
+    let dated = builtin.enabled().filter_map(|source| source.dated("20260101")).collect::<Vec<Source>>();
+    assert_eq!(dated.len(), 9);
+    assert!(dated.iter().all(|source| !source.name.contains("iana")));
+    assert!(dated.iter().any(|source| source.url == "https://ftp.afrinic.net/pub/stats/afrinic/2026/delegated-afrinic-extended-20260101"));
+    let ripencc = dated.iter().find(|source| source.name == "delegated-ripencc-latest").unwrap();
+    assert_eq!(ripencc.url, "https://ftp.ripe.net/pub/stats/ripencc/2026/delegated-ripencc-20260101.bz2");
+    assert_eq!(ripencc.checksum_url(&ripencc.url).unwrap(), "https://ftp.ripe.net/pub/stats/ripencc/2026/delegated-ripencc-20260101.bz2.md5");
+    assert_eq!(Sources::from_toml(include_str!("../../sources.example.toml")).unwrap(), builtin);
+
+    match Sources::from_toml("[[source]]\nname = \"a\"\nurl = \"https://a\"\n[[source]]\nname = \"a\"\nurl = \"https://b\"\n") {
+        Err(SourcesError::Duplicate(name)) => assert_eq!(name, "a"),
+        ret => panic!("{:?}", ret),
+    }
+    assert!(Sources::from_toml("[[source]]\nname = \"a\"\nurl = \"https://a\"\nchecksum = \"sha1\"\n").is_err());
+}
@@ -0,0 +1,91 @@
+use crate::database::{binary_search, unpack_flags};
+use crate::{Country, Date, Registry, Status};
+
+
+// Format: (first_asn, last_asn, country_index, registry_index << 4 | status_index, date)
+pub(crate) type AsnRow = (u32, u32, u8, u8, u32);
+
+
+/// A delegated range of AS numbers that contains the looked up ASN.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct AsnLookupResult {
+    first: u32,
+    last: u32,
+    country: Country,
+    registry: Registry,
+    status: Status,
+    date: Option<Date>,
+}
+
+impl AsnLookupResult {
+    /// First AS number of the delegated range.
+    #[inline]
+    pub fn first(&self) -> u32 {
+        self.first
+    }
+
+    /// Last AS number of the delegated range.
+    #[inline]
+    pub fn last(&self) -> u32 {
+        self.last
+    }
+
+    #[inline]
+    pub fn country(&self) -> Country {
+        self.country
+    }
+
+    /// The RIR which delegated this range.
+    #[inline]
+    pub fn registry(&self) -> Registry {
+        self.registry
+    }
+
+    #[inline]
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Allocation date, `None` if the RIR does not publish one.
+    #[inline]
+    pub fn date(&self) -> Option<Date> {
+        self.date
+    }
+}
+
+/// `rows` must be sorted and non-overlapping.
+pub(crate) fn lookup(rows: &[AsnRow], asn: u32) -> Option<AsnLookupResult> {
+    let pos = binary_search(rows.len(), |idx| (asn >= rows[idx].0, asn <= rows[idx].1))?;
+
+    let (first, last, cc, flags, date) = rows[pos];
+    let (registry, status) = unpack_flags(flags);
+    Some(AsnLookupResult {
+        first,
+        last,
+        country: Country::from_index(cc),
+        registry,
+        status,
+        date: Date::from_number(date).ok(),
+    })
+}
+
+
+#[test]
+fn test_lookup_asn_rows() {
+    let flags = crate::database::pack_flags(Registry::Apnic, Status::Allocated);
+    let rows = [
+        (173, 174, Country::JP.index(), flags, 20020801),
+        (4608, 4863, Country::KR.index(), flags, 0),
+    ];
+
+    let ret = lookup(&rows, 174).unwrap();
+    assert_eq!((ret.first(), ret.last()), (173, 174));
+    assert_eq!(ret.country(), Country::JP);
+    assert_eq!(ret.registry(), Registry::Apnic);
+    assert_eq!(ret.date(), Some(Date::new(2002, 8, 1).unwrap()));
+
+    assert_eq!(lookup(&rows, 4700).map(|ret| ret.country()), Some(Country::KR));
+    assert!(lookup(&rows, 175).is_none());
+    assert!(lookup(&rows, u32::MAX).is_none());
+    assert!(lookup(&[], 173).is_none());
+}
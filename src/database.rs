@@ -0,0 +1,955 @@
+use std::fmt;
+use std::io;
+use std::fs;
+use std::sync::Arc;
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::FromStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{format, net, special, v4_db, v6_db};
+use crate::format::{FormatError, Header};
+use crate::net::{IpNet, IpNetIter};
+use crate::{Classified, Country, Date, LookupResult, Registry, Status, StatusFilter};
+use crate::country::{COUNTRY_CODES, COUNTRY_CODES_LEN};
+
+
+// Format: (first_ip, last_ip, country_index, registry_index << 4 | status_index, date)
+pub(crate) type Ipv4Row = (u32, u32, u8, u8, u32);
+pub(crate) type Ipv6Row = (u128, u128, u8, u8, u32);
+// Format: (first_ip, last_ip, registry_index)
+pub(crate) type IanaIpv4Row = (u32, u32, u8);
+pub(crate) type IanaIpv6Row = (u128, u128, u8);
+
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// Malformed record, with its 1-based line number.
+    InvalidRecord(usize),
+    /// Record that starts before the end of the previous one of the same
+    /// family, out of order or overlapping, with its 1-based line number.
+    Overlapping(usize),
+    Format(FormatError),
+}
+
+impl std::error::Error for LoadError { }
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+impl From<FormatError> for LoadError {
+    fn from(e: FormatError) -> LoadError {
+        LoadError::Format(e)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::InvalidRecord(line) => write!(f, "InvalidRecord(line {})", line),
+            LoadError::Overlapping(line) => write!(f, "Overlapping(line {})", line),
+            LoadError::Format(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+
+static BUILTIN: Database = Database {
+    tables: Tables::Rows {
+        v4: Cow::Borrowed(&v4_db::IPV4_RECORDS),
+        v6: Cow::Borrowed(&v6_db::IPV6_RECORDS),
+        iana_v4: Cow::Borrowed(&v4_db::IANA_IPV4_RECORDS),
+        iana_v6: Cow::Borrowed(&v6_db::IANA_IPV6_RECORDS),
+        data_date: v4_db::DATA_DATE,
+        data_serials: &v4_db::DATA_SERIALS,
+    },
+};
+
+/// A set of sorted IPv4 and IPv6 delegation tables.
+///
+/// `Database::builtin()` is the tables generated into this crate at build time,
+/// other instances are loaded at runtime from the files written by `parse`.
+#[derive(Debug, Clone)]
+pub struct Database {
+    tables: Tables,
+}
+
+#[derive(Debug, Clone)]
+enum Tables {
+    Rows {
+        v4: Cow<'static, [Ipv4Row]>,
+        v6: Cow<'static, [Ipv6Row]>,
+        // IANA => RIR delegations
+        iana_v4: Cow<'static, [IanaIpv4Row]>,
+        iana_v6: Cow<'static, [IanaIpv6Row]>,
+        data_date: u32,
+        // (file name, serial)
+        data_serials: &'static [(&'static str, u64)],
+    },
+    Binary(Arc<Binary>),
+}
+
+/// A binary database file, rows are decoded on access.
+struct Binary {
+    buf: Buffer,
+    header: Header,
+    // File country index => `Country`
+    countries: Vec<Country>,
+}
+
+enum Buffer {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Buffer::Owned(buf) => buf,
+            #[cfg(feature = "mmap")]
+            Buffer::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+impl Binary {
+    fn new(buf: Buffer) -> Result<Self, FormatError> {
+        let header = format::read_header(&buf)?;
+
+        let mut countries = Vec::with_capacity(header.country_count as usize);
+        for idx in 0..header.country_count as usize {
+            let cc = format::country_code(&buf, &header, idx)?;
+            let country = Country::from_str(cc).map_err(|_| FormatError::InvalidCountryCode)?;
+            countries.push(country);
+        }
+
+        // NOTE: 查询用二分查找，行必须有序且互不重叠。
+        let binary = Binary { buf, header, countries };
+        let mut end = None;
+        for idx in 0..binary.header.v4_count as usize {
+            let (first, last, cc, flags, _date) = format::ipv4_row(&binary.buf, &binary.header, idx);
+            binary.check_row(cc, flags)?;
+            if first > last || end.map(|end| first <= end).unwrap_or(false) {
+                return Err(FormatError::UnsortedRows);
+            }
+            end = Some(last);
+        }
+        let mut end = None;
+        for idx in 0..binary.header.v6_count as usize {
+            let (first, last, cc, flags, _date) = format::ipv6_row(&binary.buf, &binary.header, idx);
+            binary.check_row(cc, flags)?;
+            if first > last || end.map(|end| first <= end).unwrap_or(false) {
+                return Err(FormatError::UnsortedRows);
+            }
+            end = Some(last);
+        }
+        let mut end = None;
+        for idx in 0..binary.header.iana_v4_count as usize {
+            let (first, last, registry) = format::iana_ipv4_row(&binary.buf, &binary.header, idx);
+            Registry::from_index(registry).map_err(|_| FormatError::InvalidRegistry)?;
+            if first > last || end.map(|end| first <= end).unwrap_or(false) {
+                return Err(FormatError::UnsortedRows);
+            }
+            end = Some(last);
+        }
+        let mut end = None;
+        for idx in 0..binary.header.iana_v6_count as usize {
+            let (first, last, registry) = format::iana_ipv6_row(&binary.buf, &binary.header, idx);
+            Registry::from_index(registry).map_err(|_| FormatError::InvalidRegistry)?;
+            if first > last || end.map(|end| first <= end).unwrap_or(false) {
+                return Err(FormatError::UnsortedRows);
+            }
+            end = Some(last);
+        }
+
+        Ok(binary)
+    }
+
+    // NOTE: 校验和只能发现损坏，索引越界的文件在这里拒绝，查询时才可以直接解码。
+    fn check_row(&self, cc: u8, flags: u8) -> Result<(), FormatError> {
+        if cc as usize >= self.countries.len() {
+            return Err(FormatError::InvalidCountryCode);
+        }
+        if Registry::from_index(flags >> 4).is_err() || Status::from_index(flags & 0x0f).is_err() {
+            return Err(FormatError::InvalidFlags);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn ipv4_row(&self, idx: usize) -> Ipv4Row {
+        let (first, last, cc, flags, date) = format::ipv4_row(&self.buf, &self.header, idx);
+        (first, last, self.countries[cc as usize].index(), flags, date)
+    }
+
+    #[inline]
+    fn ipv6_row(&self, idx: usize) -> Ipv6Row {
+        let (first, last, cc, flags, date) = format::ipv6_row(&self.buf, &self.header, idx);
+        (first, last, self.countries[cc as usize].index(), flags, date)
+    }
+}
+
+impl fmt::Debug for Binary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Binary")
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+impl Database {
+    /// The tables compiled into this crate.
+    #[inline]
+    pub fn builtin() -> &'static Database {
+        &BUILTIN
+    }
+
+    /// Load `v4_records` and `v6_records` from the `parse` data path, and
+    /// `iana_v4_records` and `iana_v6_records` if they exist, see
+    /// `Database::lookup_registry`.
+    pub fn open<P: AsRef<Path>>(data_path: P) -> Result<Self, LoadError> {
+        let data_path = data_path.as_ref();
+        let read_optional = |filename: &str| -> Result<String, LoadError> {
+            match fs::read_to_string(data_path.join(filename)) {
+                Ok(content) => Ok(content),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+                Err(e) => Err(LoadError::Io(e)),
+            }
+        };
+
+        let v4_content = fs::read_to_string(data_path.join("v4_records"))?;
+        let v6_content = fs::read_to_string(data_path.join("v6_records"))?;
+        let iana_v4_content = read_optional("iana_v4_records")?;
+        let iana_v6_content = read_optional("iana_v6_records")?;
+
+        Database::from_records_with_iana(&v4_content, &v6_content, &iana_v4_content, &iana_v6_content)
+    }
+
+    pub fn from_records_files<P: AsRef<Path>, Q: AsRef<Path>>(v4_path: P, v6_path: Q) -> Result<Self, LoadError> {
+        let v4_content = fs::read_to_string(v4_path)?;
+        let v6_content = fs::read_to_string(v6_path)?;
+
+        Database::from_records(&v4_content, &v6_content)
+    }
+
+    /// Parse the text records written by `parse`, one per line:
+    /// `registry cc type start value status dst_registry [date [opaque_id]]`
+    ///
+    /// `date` is `YYYYMMDD` or `none`, `opaque_id` is not kept. Records must be
+    /// sorted and must not overlap, as `parse` writes them.
+    pub fn from_records(v4_records: &str, v6_records: &str) -> Result<Self, LoadError> {
+        Database::from_records_with_iana(v4_records, v6_records, "", "")
+    }
+
+    /// Like `from_records`, with the IANA records written by `parse`, whose
+    /// `dst_registry` is the RIR IANA delegated the block to.
+    pub fn from_records_with_iana(v4_records: &str,
+                                  v6_records: &str,
+                                  iana_v4_records: &str,
+                                  iana_v6_records: &str) -> Result<Self, LoadError> {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        let mut iana_v4 = Vec::new();
+        let mut iana_v6 = Vec::new();
+
+        parse_rows(v4_records, &mut v4, &mut v6)?;
+        parse_rows(v6_records, &mut v4, &mut v6)?;
+        parse_iana_rows(iana_v4_records, &mut iana_v4, &mut iana_v6)?;
+        parse_iana_rows(iana_v6_records, &mut iana_v4, &mut iana_v6)?;
+
+        Ok(Database {
+            tables: Tables::Rows {
+                v4: Cow::Owned(v4),
+                v6: Cow::Owned(v6),
+                iana_v4: Cow::Owned(iana_v4),
+                iana_v6: Cow::Owned(iana_v6),
+                data_date: 0,
+                data_serials: &[],
+            }
+        })
+    }
+
+    /// Open a binary database file written by `parse`.
+    ///
+    /// With the `mmap` feature the file is memory-mapped and shared through the
+    /// page cache, otherwise it is read into memory. Either way rows are decoded
+    /// on access. Replace the file by renaming a new one over it, a mapped file
+    /// must not be modified in place.
+    pub fn open_binary<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        #[cfg(feature = "mmap")]
+        let buf = {
+            let file = fs::File::open(path)?;
+            // SAFETY: see above, the file is never written in place by `parse`.
+            Buffer::Mapped(unsafe { memmap2::Mmap::map(&file)? })
+        };
+        #[cfg(not(feature = "mmap"))]
+        let buf = Buffer::Owned(fs::read(path)?);
+
+        let binary = Binary::new(buf)?;
+
+        Ok(Database { tables: Tables::Binary(Arc::new(binary)) })
+    }
+
+    /// Decode a binary database from memory.
+    pub fn from_binary(buf: Vec<u8>) -> Result<Self, LoadError> {
+        let binary = Binary::new(Buffer::Owned(buf))?;
+
+        Ok(Database { tables: Tables::Binary(Arc::new(binary)) })
+    }
+
+    /// Encode this database in the binary format, see `Database::open_binary`.
+    pub fn write_binary<W: io::Write>(&self, w: W) -> io::Result<()> {
+        let country_codes = COUNTRY_CODES.iter().map(|&(cc, _)| cc).collect::<Vec<&str>>();
+        let v4 = (0..self.ipv4_len()).map(|idx| self.ipv4_row(idx)).collect::<Vec<Ipv4Row>>();
+        let v6 = (0..self.ipv6_len()).map(|idx| self.ipv6_row(idx)).collect::<Vec<Ipv6Row>>();
+        let iana_v4 = (0..self.iana_ipv4_len()).map(|idx| self.iana_ipv4_row(idx)).collect::<Vec<IanaIpv4Row>>();
+        let iana_v6 = (0..self.iana_ipv6_len()).map(|idx| self.iana_ipv6_row(idx)).collect::<Vec<IanaIpv6Row>>();
+        debug_assert_eq!(country_codes.len(), COUNTRY_CODES_LEN);
+
+        format::write(w, self.data_date().map(|date| date.number()).unwrap_or(0), &country_codes, &v4, &v6, &iana_v4, &iana_v6)
+    }
+
+    /// End date of the newest RIR statistics file this database was built
+    /// from, `None` if unknown, e.g. for `Database::from_records`.
+    pub fn data_date(&self) -> Option<Date> {
+        let number = match self.tables {
+            Tables::Rows { data_date, .. } => data_date,
+            Tables::Binary(ref binary) => binary.header.data_date,
+        };
+
+        Date::from_number(number).ok()
+    }
+
+    /// Name and serial of every RIR statistics file this database was built
+    /// from, empty if unknown, e.g. for `Database::from_records` and binary
+    /// database files.
+    pub fn data_serials(&self) -> &[(&'static str, u64)] {
+        match self.tables {
+            Tables::Rows { data_serials, .. } => data_serials,
+            Tables::Binary(_) => &[],
+        }
+    }
+
+    pub fn ipv4_len(&self) -> usize {
+        match self.tables {
+            Tables::Rows { ref v4, .. } => v4.len(),
+            Tables::Binary(ref binary) => binary.header.v4_count as usize,
+        }
+    }
+
+    pub fn ipv6_len(&self) -> usize {
+        match self.tables {
+            Tables::Rows { ref v6, .. } => v6.len(),
+            Tables::Binary(ref binary) => binary.header.v6_count as usize,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn ipv4_row(&self, idx: usize) -> Ipv4Row {
+        match self.tables {
+            Tables::Rows { ref v4, .. } => v4[idx],
+            Tables::Binary(ref binary) => binary.ipv4_row(idx),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn ipv6_row(&self, idx: usize) -> Ipv6Row {
+        match self.tables {
+            Tables::Rows { ref v6, .. } => v6[idx],
+            Tables::Binary(ref binary) => binary.ipv6_row(idx),
+        }
+    }
+
+    fn iana_ipv4_len(&self) -> usize {
+        match self.tables {
+            Tables::Rows { ref iana_v4, .. } => iana_v4.len(),
+            Tables::Binary(ref binary) => binary.header.iana_v4_count as usize,
+        }
+    }
+
+    fn iana_ipv6_len(&self) -> usize {
+        match self.tables {
+            Tables::Rows { ref iana_v6, .. } => iana_v6.len(),
+            Tables::Binary(ref binary) => binary.header.iana_v6_count as usize,
+        }
+    }
+
+    #[inline]
+    fn iana_ipv4_row(&self, idx: usize) -> IanaIpv4Row {
+        match self.tables {
+            Tables::Rows { ref iana_v4, .. } => iana_v4[idx],
+            Tables::Binary(ref binary) => format::iana_ipv4_row(&binary.buf, &binary.header, idx),
+        }
+    }
+
+    #[inline]
+    fn iana_ipv6_row(&self, idx: usize) -> IanaIpv6Row {
+        match self.tables {
+            Tables::Rows { ref iana_v6, .. } => iana_v6[idx],
+            Tables::Binary(ref binary) => format::iana_ipv6_row(&binary.buf, &binary.header, idx),
+        }
+    }
+
+    pub fn lookup(&self, ip: &IpAddr) -> Option<LookupResult> {
+        match *ip {
+            IpAddr::V4(v4_addr) => {
+                let v4_number = u32::from(v4_addr);
+                let pos = binary_search(self.ipv4_len(), |idx| {
+                    let (first, last, _cc, _flags, _date) = self.ipv4_row(idx);
+                    (v4_number >= first, v4_number <= last)
+                })?;
+
+                Some(ipv4_result(v4_number, self.ipv4_row(pos)))
+            }
+            IpAddr::V6(v6_addr) => {
+                let v6_number = u128::from(v6_addr);
+                let pos = binary_search(self.ipv6_len(), |idx| {
+                    let (first, last, _cc, _flags, _date) = self.ipv6_row(idx);
+                    (v6_number >= first, v6_number <= last)
+                })?;
+
+                Some(ipv6_result(v6_number, self.ipv6_row(pos)))
+            }
+        }
+    }
+
+    /// The RIR responsible for `ip`.
+    ///
+    /// The RIR-level delegation is used when there is one, otherwise the block
+    /// IANA delegated to a RIR, which also covers space the RIR has not
+    /// delegated yet. Without IANA records, e.g. `Database::from_records`,
+    /// only the RIR-level delegations are used.
+    pub fn lookup_registry(&self, ip: &IpAddr) -> Option<Registry> {
+        if let Some(ret) = self.lookup(ip) {
+            return Some(ret.registry());
+        }
+
+        let index = match *ip {
+            IpAddr::V4(v4_addr) => {
+                let number = u32::from(v4_addr);
+                let pos = binary_search(self.iana_ipv4_len(), |idx| {
+                    let (first, last, _registry) = self.iana_ipv4_row(idx);
+                    (number >= first, number <= last)
+                })?;
+                self.iana_ipv4_row(pos).2
+            }
+            IpAddr::V6(v6_addr) => {
+                let number = u128::from(v6_addr);
+                let pos = binary_search(self.iana_ipv6_len(), |idx| {
+                    let (first, last, _registry) = self.iana_ipv6_row(idx);
+                    (number >= first, number <= last)
+                })?;
+                self.iana_ipv6_row(pos).2
+            }
+        };
+
+        Registry::from_index(index).ok()
+    }
+
+    /// Like `lookup`, but blocks whose status is not in `filter` do not match.
+    ///
+    /// `lookup` matches every status, including blocks the RIRs publish as
+    /// `Available` or `Reserved` (country `ZZ`). With `StatusFilter::DELEGATED`
+    /// such unallocated space is `None`, and can be treated as bogon traffic.
+    pub fn lookup_filtered(&self, ip: &IpAddr, filter: StatusFilter) -> Option<LookupResult> {
+        self.lookup(ip).filter(|ret| filter.contains(ret.status()))
+    }
+
+    /// Like `lookup`, but addresses in the IANA special-purpose registries are
+    /// classified first, e.g. `10.0.0.1` is `SpecialPurpose::Private`.
+    pub fn classify(&self, ip: &IpAddr) -> Option<Classified> {
+        match special::lookup(ip) {
+            Some((kind, net)) => Some(Classified::Special(kind, net)),
+            None => self.lookup(ip).map(Classified::Delegated),
+        }
+    }
+}
+
+impl Database {
+    /// Every range delegated to `country`, IPv4 first, both in ascending order.
+    pub fn ranges_for(&self, country: Country) -> Ranges<'_> {
+        Ranges { db: self, country, v4_idx: 0, v6_idx: 0 }
+    }
+
+    /// The minimal list of prefixes covering every range delegated to `country`.
+    ///
+    /// Adjacent ranges are merged before they are split into prefixes.
+    pub fn cidrs_for(&self, country: Country) -> Cidrs<'_> {
+        Cidrs { ranges: self.ranges_for(country).peekable(), nets: None }
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        BUILTIN.clone()
+    }
+}
+
+
+/// Iterator returned by `Database::ranges_for`, yields `(first, last)`.
+#[derive(Debug, Clone)]
+pub struct Ranges<'a> {
+    db: &'a Database,
+    country: Country,
+    v4_idx: usize,
+    v6_idx: usize,
+}
+
+impl<'a> Iterator for Ranges<'a> {
+    type Item = (IpAddr, IpAddr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cc = self.country.index();
+
+        while self.v4_idx < self.db.ipv4_len() {
+            let (first, last, row_cc, _flags, _date) = self.db.ipv4_row(self.v4_idx);
+            self.v4_idx += 1;
+            if row_cc == cc {
+                return Some((IpAddr::from(Ipv4Addr::from(first)), IpAddr::from(Ipv4Addr::from(last))));
+            }
+        }
+
+        while self.v6_idx < self.db.ipv6_len() {
+            let (first, last, row_cc, _flags, _date) = self.db.ipv6_row(self.v6_idx);
+            self.v6_idx += 1;
+            if row_cc == cc {
+                return Some((IpAddr::from(Ipv6Addr::from(first)), IpAddr::from(Ipv6Addr::from(last))));
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator returned by `Database::cidrs_for`.
+#[derive(Debug, Clone)]
+pub struct Cidrs<'a> {
+    ranges: Peekable<Ranges<'a>>,
+    nets: Option<IpNetIter>,
+}
+
+impl<'a> Iterator for Cidrs<'a> {
+    type Item = IpNet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(net) = self.nets.as_mut().and_then(|nets| nets.next()) {
+                return Some(net);
+            }
+
+            let (first, mut last) = self.ranges.next()?;
+            while let Some(&(next_first, next_last)) = self.ranges.peek() {
+                if !is_successor(last, next_first) {
+                    break;
+                }
+                last = next_last;
+                self.ranges.next();
+            }
+
+            self.nets = Some(IpNetIter::new(first, last));
+        }
+    }
+}
+
+fn is_successor(last: IpAddr, next: IpAddr) -> bool {
+    match (last, next) {
+        (IpAddr::V4(last), IpAddr::V4(next)) => u32::from(last).checked_add(1) == Some(u32::from(next)),
+        (IpAddr::V6(last), IpAddr::V6(next)) => u128::from(last).checked_add(1) == Some(u128::from(next)),
+        _ => false,
+    }
+}
+
+
+/// Binary search over `len` sorted, non-overlapping ranges.
+/// 
+/// `probe(idx)` returns `(number >= first, number <= last)` of the range at `idx`.
+pub(crate) fn binary_search<F: Fn(usize) -> (bool, bool)>(len: usize, probe: F) -> Option<usize> {
+    let mut low = 0usize;
+    let mut high = len;
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match probe(mid) {
+            (true, true) => return Some(mid),
+            (true, false) => low = mid + 1,
+            (false, _) => high = mid,
+        }
+    }
+
+    None
+}
+
+
+/// The result of looking up `v4_number` in the table, `row` contains it.
+pub(crate) fn ipv4_result(v4_number: u32, row: Ipv4Row) -> LookupResult {
+    let (first, last, cc, flags, date) = row;
+    let (registry, status) = unpack_flags(flags);
+
+    LookupResult {
+        first: IpAddr::from(Ipv4Addr::from(first)),
+        last: IpAddr::from(Ipv4Addr::from(last)),
+        network: net::v4_enclosing_net(v4_number, first, last),
+        country: Country::from_index(cc),
+        registry,
+        status,
+        date: Date::from_number(date).ok(),
+    }
+}
+
+pub(crate) fn ipv6_result(v6_number: u128, row: Ipv6Row) -> LookupResult {
+    let (first, last, cc, flags, date) = row;
+    let (registry, status) = unpack_flags(flags);
+
+    LookupResult {
+        first: IpAddr::from(Ipv6Addr::from(first)),
+        last: IpAddr::from(Ipv6Addr::from(last)),
+        network: net::v6_enclosing_net(v6_number, first, last),
+        country: Country::from_index(cc),
+        registry,
+        status,
+        date: Date::from_number(date).ok(),
+    }
+}
+
+
+// Format: registry_index << 4 | status_index
+pub(crate) fn pack_flags(registry: Registry, status: Status) -> u8 {
+    registry.index() << 4 | status.index()
+}
+
+pub(crate) fn unpack_flags(flags: u8) -> (Registry, Status) {
+    let registry = Registry::from_index(flags >> 4).expect("invalid registry index");
+    let status = Status::from_index(flags & 0x0f).expect("invalid status index");
+    (registry, status)
+}
+
+
+enum Row {
+    V4(Ipv4Row),
+    V6(Ipv6Row),
+}
+
+fn parse_rows(content: &str, v4: &mut Vec<Ipv4Row>, v6: &mut Vec<Ipv6Row>) -> Result<(), LoadError> {
+    for (line_idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // NOTE: lookup 用二分查找，乱序或者重叠的记录会查到错误的结果。
+        match parse_row(line).ok_or(LoadError::InvalidRecord(line_idx + 1))? {
+            Row::V4(row) => {
+                if v4.last().map(|last: &Ipv4Row| row.0 <= last.1).unwrap_or(false) {
+                    return Err(LoadError::Overlapping(line_idx + 1));
+                }
+                v4.push(row);
+            },
+            Row::V6(row) => {
+                if v6.last().map(|last: &Ipv6Row| row.0 <= last.1).unwrap_or(false) {
+                    return Err(LoadError::Overlapping(line_idx + 1));
+                }
+                v6.push(row);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_iana_rows(content: &str, v4: &mut Vec<IanaIpv4Row>, v6: &mut Vec<IanaIpv6Row>) -> Result<(), LoadError> {
+    for (line_idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let registry = line.split_whitespace().nth(6).and_then(|s| Registry::from_str(s).ok());
+        match (parse_row(line), registry) {
+            (Some(Row::V4((first, last, ..))), Some(registry)) => {
+                if v4.last().map(|prev: &IanaIpv4Row| first <= prev.1).unwrap_or(false) {
+                    return Err(LoadError::Overlapping(line_idx + 1));
+                }
+                v4.push((first, last, registry.index()));
+            },
+            (Some(Row::V6((first, last, ..))), Some(registry)) => {
+                if v6.last().map(|prev: &IanaIpv6Row| first <= prev.1).unwrap_or(false) {
+                    return Err(LoadError::Overlapping(line_idx + 1));
+                }
+                v6.push((first, last, registry.index()));
+            },
+            _ => return Err(LoadError::InvalidRecord(line_idx + 1)),
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_row(line: &str) -> Option<Row> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 7 {
+        return None;
+    }
+
+    let registry = Registry::from_str(fields[0]).ok()?;
+    let country = Country::from_str(fields[1]).ok()?;
+    let status = Status::from_str(fields[5]).ok()?;
+    let flags = pack_flags(registry, status);
+    let date = match fields.get(7) {
+        None | Some(&"none") => 0,
+        Some(date) => Date::from_str(date).ok()?.number(),
+    };
+
+    match fields[2] {
+        "ipv4" => {
+            let start: Ipv4Addr = fields[3].parse().ok()?;
+            let nums: u32 = fields[4].parse().ok()?;
+            if nums == 0 {
+                return None;
+            }
+            let first = u32::from(start);
+            let last = first.checked_add(nums - 1)?;
+
+            Some(Row::V4((first, last, country.index(), flags, date)))
+        }
+        "ipv6" => {
+            let start: Ipv6Addr = fields[3].parse().ok()?;
+            let prefix_len: u32 = fields[4].parse().ok()?;
+            if prefix_len > 128 {
+                return None;
+            }
+            let first = u128::from(start);
+            let last = if prefix_len == 0 { u128::MAX } else { first | (u128::MAX >> prefix_len) };
+
+            Some(Row::V6((first, last, country.index(), flags, date)))
+        }
+        _ => None,
+    }
+}
+
+
+#[test]
+fn test_database_from_records() {
+    let v4_records = "\
+apnic CN ipv4 1.0.1.0 256 allocated none
+apnic CN ipv4 1.0.2.0 512 allocated none 20110414 A92D9378
+";
+    let v6_records = "\
+apnic JP ipv6 2001:218:: 32 allocated none none
+";
+    let db = Database::from_records(v4_records, v6_records).unwrap();
+    assert_eq!(db.ipv4_len(), 2);
+    assert_eq!(db.ipv6_len(), 1);
+
+    let ret = db.lookup(&"1.0.3.1".parse().unwrap()).unwrap();
+    assert_eq!(ret.country(), Country::CN);
+    assert_eq!(ret.registry(), Registry::Apnic);
+    assert_eq!(ret.status(), Status::Allocated);
+    assert_eq!(ret.first(), "1.0.2.0".parse::<IpAddr>().unwrap());
+    assert_eq!(ret.last(), "1.0.3.255".parse::<IpAddr>().unwrap());
+    assert_eq!(ret.date(), Some(Date::new(2011, 4, 14).unwrap()));
+
+    let ret = db.lookup(&"2001:218:ffff::1".parse().unwrap()).unwrap();
+    assert_eq!(ret.country().code(), "JP");
+    assert_eq!(ret.date(), None);
+    assert!(db.lookup(&"2001:219::1".parse().unwrap()).is_none());
+
+    match Database::from_records("apnic CN ipv4 1.0.1.0 0 allocated none", "") {
+        Err(LoadError::InvalidRecord(1)) => { },
+        ret => panic!("{:?}", ret),
+    }
+    match Database::from_records("apnic CN ipv4 1.0.1.0 256 allocated none 2011-04-14", "") {
+        Err(LoadError::InvalidRecord(1)) => { },
+        ret => panic!("{:?}", ret),
+    }
+    match Database::from_records("apnic CN ipv4 1.0.2.0 256 allocated none\napnic CN ipv4 1.0.1.0 256 allocated none", "") {
+        Err(LoadError::Overlapping(2)) => { },
+        ret => panic!("{:?}", ret),
+    }
+    match Database::from_records("apnic CN ipv4 1.0.0.0 512 allocated none\napnic CN ipv4 1.0.1.0 256 allocated none", "") {
+        Err(LoadError::Overlapping(2)) => { },
+        ret => panic!("{:?}", ret),
+    }
+    match Database::from_records("", "apnic JP ipv6 2001:218:: 32 allocated none\napnic JP ipv6 2001:218:1:: 48 allocated none") {
+        Err(LoadError::Overlapping(2)) => { },
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[test]
+fn test_database_lookup_filtered() {
+    let v4_records = "\
+apnic CN ipv4 1.0.1.0 256 allocated none
+arin ZZ ipv4 23.128.0.0 1024 reserved none
+lacnic ZZ ipv4 45.5.0.0 256 available none
+";
+    let db = Database::from_records(v4_records, "").unwrap();
+
+    let ip = "23.128.1.1".parse().unwrap();
+    assert_eq!(db.lookup(&ip).map(|ret| ret.status()), Some(Status::Reserved));
+    assert_eq!(db.lookup_filtered(&ip, StatusFilter::ALL), db.lookup(&ip));
+    assert!(db.lookup_filtered(&ip, StatusFilter::DELEGATED).is_none());
+    assert!(db.lookup_filtered(&ip, StatusFilter::DELEGATED.with(Status::Reserved)).is_some());
+
+    let ip = "45.5.0.1".parse().unwrap();
+    assert!(db.lookup_filtered(&ip, StatusFilter::DELEGATED).is_none());
+    assert!(db.lookup_filtered(&ip, StatusFilter::ALL.without(Status::Available)).is_none());
+
+    let ip = "1.0.1.1".parse().unwrap();
+    assert_eq!(db.lookup_filtered(&ip, StatusFilter::DELEGATED).map(|ret| ret.country()), Some(Country::CN));
+    assert!(db.lookup_filtered(&ip, StatusFilter::NONE).is_none());
+}
+
+#[test]
+fn test_database_binary() {
+    let v4_records = "\
+apnic CN ipv4 1.0.1.0 256 allocated none
+arin US ipv4 8.0.0.0 16777216 allocated none
+";
+    let v6_records = "\
+apnic JP ipv6 2001:218:: 32 assigned none
+";
+    let db = Database::from_records(v4_records, v6_records).unwrap();
+
+    let mut buf = Vec::new();
+    db.write_binary(&mut buf).unwrap();
+    let binary_db = Database::from_binary(buf).unwrap();
+    assert_eq!(binary_db.ipv4_len(), 2);
+    assert_eq!(binary_db.ipv6_len(), 1);
+    assert_eq!(binary_db.data_date(), None);
+    assert!(binary_db.data_serials().is_empty());
+
+    for ip in ["1.0.1.0", "1.0.1.255", "8.8.8.8", "8.255.255.255", "9.0.0.0", "2001:218::1", "2001:219::"].iter() {
+        let ip = ip.parse::<IpAddr>().unwrap();
+        assert_eq!(binary_db.lookup(&ip), db.lookup(&ip));
+    }
+
+    match Database::from_binary(b"IANAIPDB".to_vec()) {
+        Err(LoadError::Format(FormatError::Truncated)) => { },
+        ret => panic!("{:?}", ret),
+    }
+
+    // 校验和正确但行的顺序错误
+    let country_codes = COUNTRY_CODES.iter().map(|&(cc, _)| cc).collect::<Vec<&str>>();
+    let rows = [
+        vec![(134217728u32, 150994943u32, 0u8, 0u8, 0u32), (16777472, 16777727, 0, 0, 0)],
+        vec![(16777472, 16777727, 0, 0, 0), (16777600, 16777983, 0, 0, 0)],
+        vec![(16777727, 16777472, 0, 0, 0)],
+    ];
+    for v4 in rows.iter() {
+        let mut buf = Vec::new();
+        format::write(&mut buf, 0, &country_codes, v4, &[], &[], &[]).unwrap();
+        match Database::from_binary(buf) {
+            Err(LoadError::Format(FormatError::UnsortedRows)) => { },
+            ret => panic!("{:?}", ret),
+        }
+    }
+}
+
+#[test]
+fn test_database_lookup_registry() {
+    let v4_records = "\
+apnic CN ipv4 1.0.1.0 256 allocated none
+";
+    let iana_v4_records = "\
+iana ZZ ipv4 0.0.0.0 16777216 assigned ietf 19810901
+iana ZZ ipv4 1.0.0.0 16777216 allocated apnic 20100101
+iana ZZ ipv4 5.0.0.0 16777216 allocated ripencc 20101101
+";
+    let iana_v6_records = "\
+iana ZZ ipv6 2001:200:: 23 allocated apnic 19990701
+";
+    let db = Database::from_records_with_iana(v4_records, "", iana_v4_records, iana_v6_records).unwrap();
+    assert_eq!(db.lookup_registry(&"1.0.1.1".parse().unwrap()), Some(Registry::Apnic));
+    assert_eq!(db.lookup_registry(&"1.0.0.1".parse().unwrap()), Some(Registry::Apnic));
+    assert_eq!(db.lookup_registry(&"5.5.5.5".parse().unwrap()), Some(Registry::Ripencc));
+    assert_eq!(db.lookup_registry(&"2001:200::1".parse().unwrap()), Some(Registry::Apnic));
+    assert_eq!(db.lookup_registry(&"8.8.8.8".parse().unwrap()), None);
+
+    let mut buf = Vec::new();
+    db.write_binary(&mut buf).unwrap();
+    let binary_db = Database::from_binary(buf).unwrap();
+    for ip in ["0.0.0.1", "1.0.0.1", "1.0.1.1", "5.5.5.5", "8.8.8.8", "2001:200::1", "2001:400::1"].iter() {
+        let ip = ip.parse::<IpAddr>().unwrap();
+        assert_eq!(binary_db.lookup_registry(&ip), db.lookup_registry(&ip));
+    }
+
+    // 没有 IANA 记录时只用 RIR 的记录
+    let db = Database::from_records(v4_records, "").unwrap();
+    assert_eq!(db.lookup_registry(&"1.0.0.1".parse().unwrap()), None);
+
+    assert_eq!(Database::builtin().lookup_registry(&"1.0.0.1".parse().unwrap()), Some(Registry::Apnic));
+    assert_eq!(Database::builtin().lookup_registry(&"2001:200::1".parse().unwrap()), Some(Registry::Apnic));
+
+    match Database::from_records_with_iana("", "", "iana ZZ ipv4 1.0.0.0 16777216 allocated none", "") {
+        Err(LoadError::InvalidRecord(1)) => { },
+        ret => panic!("{:?}", ret),
+    }
+}
+
+#[test]
+fn test_database_ranges_for() {
+    let v4_records = "\
+apnic CN ipv4 1.0.1.0 256 allocated none
+apnic CN ipv4 1.0.2.0 512 allocated none
+apnic AU ipv4 1.0.4.0 256 assigned none
+apnic CN ipv4 1.0.8.0 2048 allocated none
+";
+    let v6_records = "\
+apnic JP ipv6 2001:218:: 32 allocated none
+apnic CN ipv6 2001:250:: 31 allocated none
+";
+    let db = Database::from_records(v4_records, v6_records).unwrap();
+
+    let ranges = db.ranges_for(Country::CN).map(|(first, last)| format!("{} - {}", first, last)).collect::<Vec<_>>();
+    assert_eq!(ranges, vec!["1.0.1.0 - 1.0.1.255",
+                            "1.0.2.0 - 1.0.3.255",
+                            "1.0.8.0 - 1.0.15.255",
+                            "2001:250:: - 2001:251:ffff:ffff:ffff:ffff:ffff:ffff"]);
+
+    let cidrs = db.cidrs_for(Country::CN).map(|net| net.to_string()).collect::<Vec<_>>();
+    assert_eq!(cidrs, vec!["1.0.1.0/24", "1.0.2.0/23", "1.0.8.0/21", "2001:250::/31"]);
+
+    assert_eq!(db.cidrs_for(Country::US).count(), 0);
+}
+
+#[test]
+fn test_database_builtin_row_bounds() {
+    let db = Database::builtin();
+
+    for idx in 0..db.ipv4_len() {
+        let row = db.ipv4_row(idx);
+        assert!(row.0 <= row.1, "{:?}", row);
+        for &number in [row.0, row.1].iter() {
+            let ret = db.lookup(&IpAddr::from(Ipv4Addr::from(number))).unwrap();
+            assert_eq!((ret.first, ret.last), (IpAddr::from(Ipv4Addr::from(row.0)), IpAddr::from(Ipv4Addr::from(row.1))));
+        }
+    }
+
+    for idx in 0..db.ipv6_len() {
+        let row = db.ipv6_row(idx);
+        assert!(row.0 <= row.1, "{:?}", row);
+        for &number in [row.0, row.1].iter() {
+            let ret = db.lookup(&IpAddr::from(Ipv6Addr::from(number))).unwrap();
+            assert_eq!((ret.first, ret.last), (IpAddr::from(Ipv6Addr::from(row.0)), IpAddr::from(Ipv6Addr::from(row.1))));
+        }
+    }
+}
+
+#[test]
+fn test_database_builtin_serials() {
+    let db = Database::builtin();
+
+    assert!(!db.data_serials().is_empty());
+    assert!(db.data_serials().iter().all(|&(filename, serial)| filename.starts_with("delegated-") && serial > 0));
+    assert!(Database::from_records("", "").unwrap().data_serials().is_empty());
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::str::FromStr;
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct InvalidDate;
+
+impl std::error::Error for InvalidDate { }
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InvalidDate")
+    }
+}
+
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+
+/// Calendar date as used by the RIR statistics exchange format (`YYYYMMDD`).
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self, InvalidDate> {
+        if year == 0 || month == 0 || month > 12 || day == 0 || day > days_in_month(year, month) {
+            return Err(InvalidDate);
+        }
+
+        Ok(Date { year, month, day })
+    }
+
+    /// `20110414` => `2011-04-14`
+    pub fn from_number(n: u32) -> Result<Self, InvalidDate> {
+        if n > 99991231 {
+            return Err(InvalidDate);
+        }
+
+        Date::new((n / 10000) as u16, (n / 100 % 100) as u8, (n % 100) as u8)
+    }
+
+    pub fn number(&self) -> u32 {
+        self.year as u32 * 10000 + self.month as u32 * 100 + self.day as u32
+    }
+
+    /// Days since `1970-01-01`.
+    pub fn days(&self) -> i64 {
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let (month, day) = (self.month as i64, self.day as i64);
+        let year = self.year as i64 - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146097 + doe - 719468
+    }
+
+    /// Inverse of `days`.
+    pub fn from_days(days: i64) -> Result<Self, InvalidDate> {
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        if !(1..=9999).contains(&year) {
+            return Err(InvalidDate);
+        }
+
+        Date::new(year as u16, month as u8, day as u8)
+    }
+
+    #[inline]
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    #[inline]
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    #[inline]
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+impl FromStr for Date {
+    type Err = InvalidDate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 8 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(InvalidDate);
+        }
+
+        let n: u32 = s.parse().map_err(|_| InvalidDate)?;
+
+        Date::from_number(n)
+    }
+}
+
+impl fmt::Debug for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}{:02}{:02}", self.year, self.month, self.day)
+    }
+}
+
+
+#[test]
+fn test_date_round_trip() {
+    let date = "20110414".parse::<Date>().unwrap();
+    assert_eq!(date, Date::new(2011, 4, 14).unwrap());
+    assert_eq!(date.number(), 20110414);
+    assert_eq!(Date::from_number(20110414), Ok(date));
+    assert_eq!(format!("{}", date), "20110414");
+
+    assert_eq!("".parse::<Date>(), Err(InvalidDate));
+    assert_eq!("00000000".parse::<Date>(), Err(InvalidDate));
+    assert_eq!("2011041".parse::<Date>(), Err(InvalidDate));
+
+    assert_eq!("20260231".parse::<Date>(), Err(InvalidDate));
+    assert_eq!("20250229".parse::<Date>(), Err(InvalidDate));
+    assert_eq!("19000229".parse::<Date>(), Err(InvalidDate));
+    assert_eq!("20260431".parse::<Date>(), Err(InvalidDate));
+    assert!("20240229".parse::<Date>().is_ok());
+    assert!("20000229".parse::<Date>().is_ok());
+}
+
+#[test]
+fn test_date_days() {
+    assert_eq!(Date::new(1970, 1, 1).unwrap().days(), 0);
+    assert_eq!(Date::new(2000, 3, 1).unwrap().days(), 11017);
+    assert_eq!(Date::new(1969, 12, 31).unwrap().days(), -1);
+
+    for &n in [19830101, 20000229, 20201231, 20240229, 20260228, 20261016].iter() {
+        let date = Date::from_number(n).unwrap();
+        assert_eq!(Date::from_days(date.days()), Ok(date));
+    }
+    assert_eq!(Date::from_days(Date::new(2024, 2, 28).unwrap().days() + 2), Ok(Date::new(2024, 3, 1).unwrap()));
+    assert_eq!(Date::from_days(-719529), Err(InvalidDate));
+}
@@ -0,0 +1,304 @@
+// Binary database format
+//
+// All integers are little endian.
+//
+//      offset  size
+//      0       8       magic, "IANAIPDB"
+//      8       2       version
+//      10      2       country_count
+//      12      4       v4_count
+//      16      4       v6_count
+//      20      4       checksum, CRC-32 (IEEE) of everything after the header
+//      24      4       data_date, YYYYMMDD end date of the newest source file, 0 if unknown
+//      28      4       iana_v4_count
+//      32      4       iana_v6_count
+//      36      4       reserved, zero
+//      40      ..      country table, country_count * 2 bytes country code (ASCII)
+//      ..      ..      v4 section, v4_count * 14 bytes, sorted by first_ip
+//      ..      ..      v6 section, v6_count * 38 bytes, sorted by first_ip
+//      ..      ..      IANA v4 section, iana_v4_count * 9 bytes, sorted by first_ip
+//      ..      ..      IANA v6 section, iana_v6_count * 33 bytes, sorted by first_ip
+//
+// Row format: first_ip, last_ip, country_index, registry_index << 4 | status_index, date
+// where country_index refers to the country table of this file.
+//
+// IANA row format: first_ip, last_ip, registry_index, the RIR IANA delegated
+// the block to.
+use std::fmt;
+use std::io::{self, Write};
+use std::convert::TryInto;
+
+
+pub const MAGIC: [u8; 8] = *b"IANAIPDB";
+pub const VERSION: u16 = 2;
+
+pub const HEADER_LEN: usize = 40;
+pub const IPV4_ROW_LEN: usize = 4 + 4 + 1 + 1 + 4;
+pub const IPV6_ROW_LEN: usize = 16 + 16 + 1 + 1 + 4;
+pub const IANA_IPV4_ROW_LEN: usize = 4 + 4 + 1;
+pub const IANA_IPV6_ROW_LEN: usize = 16 + 16 + 1;
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum FormatError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    ChecksumMismatch,
+    InvalidCountryCode,
+    InvalidFlags,
+    InvalidRegistry,
+    /// A row that ends before it starts, or starts before the end of the
+    /// previous row of the same section.
+    UnsortedRows,
+}
+
+impl std::error::Error for FormatError { }
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u16,
+    pub country_count: u16,
+    pub v4_count: u32,
+    pub v6_count: u32,
+    pub checksum: u32,
+    pub data_date: u32,
+    pub iana_v4_count: u32,
+    pub iana_v6_count: u32,
+}
+
+impl Header {
+    pub fn country_table_offset(&self) -> usize {
+        HEADER_LEN
+    }
+
+    pub fn v4_offset(&self) -> usize {
+        self.country_table_offset() + self.country_count as usize * 2
+    }
+
+    pub fn v6_offset(&self) -> usize {
+        self.v4_offset() + self.v4_count as usize * IPV4_ROW_LEN
+    }
+
+    pub fn iana_v4_offset(&self) -> usize {
+        self.v6_offset() + self.v6_count as usize * IPV6_ROW_LEN
+    }
+
+    pub fn iana_v6_offset(&self) -> usize {
+        self.iana_v4_offset() + self.iana_v4_count as usize * IANA_IPV4_ROW_LEN
+    }
+
+    pub fn file_len(&self) -> usize {
+        self.iana_v6_offset() + self.iana_v6_count as usize * IANA_IPV6_ROW_LEN
+    }
+}
+
+/// Decode and verify the header against the whole file content.
+pub fn read_header(buf: &[u8]) -> Result<Header, FormatError> {
+    if buf.len() < HEADER_LEN {
+        return Err(FormatError::Truncated);
+    }
+    if buf[..8] != MAGIC {
+        return Err(FormatError::BadMagic);
+    }
+
+    let header = Header {
+        version: u16::from_le_bytes(buf[8..10].try_into().unwrap()),
+        country_count: u16::from_le_bytes(buf[10..12].try_into().unwrap()),
+        v4_count: u32::from_le_bytes(buf[12..16].try_into().unwrap()),
+        v6_count: u32::from_le_bytes(buf[16..20].try_into().unwrap()),
+        checksum: u32::from_le_bytes(buf[20..24].try_into().unwrap()),
+        data_date: u32::from_le_bytes(buf[24..28].try_into().unwrap()),
+        iana_v4_count: u32::from_le_bytes(buf[28..32].try_into().unwrap()),
+        iana_v6_count: u32::from_le_bytes(buf[32..36].try_into().unwrap()),
+    };
+
+    if header.version != VERSION {
+        return Err(FormatError::UnsupportedVersion(header.version));
+    }
+    if buf.len() != header.file_len() {
+        return Err(FormatError::Truncated);
+    }
+    if crc32(&buf[HEADER_LEN..]) != header.checksum {
+        return Err(FormatError::ChecksumMismatch);
+    }
+
+    Ok(header)
+}
+
+pub fn country_code<'a>(buf: &'a [u8], header: &Header, idx: usize) -> Result<&'a str, FormatError> {
+    let offset = header.country_table_offset() + idx * 2;
+
+    std::str::from_utf8(&buf[offset..offset + 2]).map_err(|_| FormatError::InvalidCountryCode)
+}
+
+#[inline]
+pub fn ipv4_row(buf: &[u8], header: &Header, idx: usize) -> (u32, u32, u8, u8, u32) {
+    let offset = header.v4_offset() + idx * IPV4_ROW_LEN;
+    let row = &buf[offset..offset + IPV4_ROW_LEN];
+
+    (u32::from_le_bytes(row[0..4].try_into().unwrap()),
+     u32::from_le_bytes(row[4..8].try_into().unwrap()),
+     row[8],
+     row[9],
+     u32::from_le_bytes(row[10..14].try_into().unwrap()))
+}
+
+#[inline]
+pub fn ipv6_row(buf: &[u8], header: &Header, idx: usize) -> (u128, u128, u8, u8, u32) {
+    let offset = header.v6_offset() + idx * IPV6_ROW_LEN;
+    let row = &buf[offset..offset + IPV6_ROW_LEN];
+
+    (u128::from_le_bytes(row[0..16].try_into().unwrap()),
+     u128::from_le_bytes(row[16..32].try_into().unwrap()),
+     row[32],
+     row[33],
+     u32::from_le_bytes(row[34..38].try_into().unwrap()))
+}
+
+#[inline]
+pub fn iana_ipv4_row(buf: &[u8], header: &Header, idx: usize) -> (u32, u32, u8) {
+    let offset = header.iana_v4_offset() + idx * IANA_IPV4_ROW_LEN;
+    let row = &buf[offset..offset + IANA_IPV4_ROW_LEN];
+
+    (u32::from_le_bytes(row[0..4].try_into().unwrap()),
+     u32::from_le_bytes(row[4..8].try_into().unwrap()),
+     row[8])
+}
+
+#[inline]
+pub fn iana_ipv6_row(buf: &[u8], header: &Header, idx: usize) -> (u128, u128, u8) {
+    let offset = header.iana_v6_offset() + idx * IANA_IPV6_ROW_LEN;
+    let row = &buf[offset..offset + IANA_IPV6_ROW_LEN];
+
+    (u128::from_le_bytes(row[0..16].try_into().unwrap()),
+     u128::from_le_bytes(row[16..32].try_into().unwrap()),
+     row[32])
+}
+
+/// Every section must be sorted by `first_ip`.
+pub fn write<W: Write>(mut w: W,
+                       data_date: u32,
+                       country_codes: &[&str],
+                       v4: &[(u32, u32, u8, u8, u32)],
+                       v6: &[(u128, u128, u8, u8, u32)],
+                       iana_v4: &[(u32, u32, u8)],
+                       iana_v6: &[(u128, u128, u8)]) -> io::Result<()> {
+    let mut body: Vec<u8> = Vec::with_capacity(country_codes.len() * 2
+                                               + v4.len() * IPV4_ROW_LEN
+                                               + v6.len() * IPV6_ROW_LEN
+                                               + iana_v4.len() * IANA_IPV4_ROW_LEN
+                                               + iana_v6.len() * IANA_IPV6_ROW_LEN);
+
+    for cc in country_codes.iter() {
+        assert_eq!(cc.len(), 2);
+        body.extend_from_slice(cc.as_bytes());
+    }
+    for &(first, last, cc, flags, date) in v4.iter() {
+        body.extend_from_slice(&first.to_le_bytes());
+        body.extend_from_slice(&last.to_le_bytes());
+        body.push(cc);
+        body.push(flags);
+        body.extend_from_slice(&date.to_le_bytes());
+    }
+    for &(first, last, cc, flags, date) in v6.iter() {
+        body.extend_from_slice(&first.to_le_bytes());
+        body.extend_from_slice(&last.to_le_bytes());
+        body.push(cc);
+        body.push(flags);
+        body.extend_from_slice(&date.to_le_bytes());
+    }
+    for &(first, last, registry) in iana_v4.iter() {
+        body.extend_from_slice(&first.to_le_bytes());
+        body.extend_from_slice(&last.to_le_bytes());
+        body.push(registry);
+    }
+    for &(first, last, registry) in iana_v6.iter() {
+        body.extend_from_slice(&first.to_le_bytes());
+        body.extend_from_slice(&last.to_le_bytes());
+        body.push(registry);
+    }
+
+    let mut header = [0u8; HEADER_LEN];
+    header[0..8].copy_from_slice(&MAGIC);
+    header[8..10].copy_from_slice(&VERSION.to_le_bytes());
+    header[10..12].copy_from_slice(&(country_codes.len() as u16).to_le_bytes());
+    header[12..16].copy_from_slice(&(v4.len() as u32).to_le_bytes());
+    header[16..20].copy_from_slice(&(v6.len() as u32).to_le_bytes());
+    header[20..24].copy_from_slice(&crc32(&body).to_le_bytes());
+    header[24..28].copy_from_slice(&data_date.to_le_bytes());
+    header[28..32].copy_from_slice(&(iana_v4.len() as u32).to_le_bytes());
+    header[32..36].copy_from_slice(&(iana_v6.len() as u32).to_le_bytes());
+
+    w.write_all(&header)?;
+    w.write_all(&body)?;
+
+    Ok(())
+}
+
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes.iter() {
+        crc = CRC32_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+}
+
+#[test]
+fn test_format_round_trip() {
+    let v4 = [(16777472u32, 16777727u32, 0u8, 0x10u8, 20110414u32)];
+    let v6 = [(1u128 << 125, (1u128 << 125) | 0xffff, 1u8, 0x61u8, 0u32)];
+    let iana_v4 = [(16777216u32, 33554431u32, 1u8), (33554432, 50331647, 2)];
+    let iana_v6 = [(0x2001_0200u128 << 96, (0x2001_0200u128 << 96) | (u128::MAX >> 23), 1u8)];
+
+    let mut buf = Vec::new();
+    write(&mut buf, 20261015, &["CN", "JP"], &v4, &v6, &iana_v4, &iana_v6).unwrap();
+
+    let header = read_header(&buf).unwrap();
+    assert_eq!(header.data_date, 20261015);
+    assert_eq!(header.v4_count, 1);
+    assert_eq!(header.v6_count, 1);
+    assert_eq!(country_code(&buf, &header, 1), Ok("JP"));
+    assert_eq!(ipv4_row(&buf, &header, 0), v4[0]);
+    assert_eq!(ipv6_row(&buf, &header, 0), v6[0]);
+    assert_eq!((header.iana_v4_count, header.iana_v6_count), (2, 1));
+    assert_eq!(iana_ipv4_row(&buf, &header, 1), iana_v4[1]);
+    assert_eq!(iana_ipv6_row(&buf, &header, 0), iana_v6[0]);
+
+    let last = buf.len() - 1;
+    buf[last] ^= 0xff;
+    assert_eq!(read_header(&buf), Err(FormatError::ChecksumMismatch));
+    assert_eq!(read_header(&buf[..last]), Err(FormatError::Truncated));
+}
@@ -0,0 +1,377 @@
+use std::fs;
+use std::sync::Arc;
+use std::path::Path;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use crate::database::{binary_search, ipv4_result, ipv6_result, Ipv4Row, Ipv6Row};
+use crate::{Country, Database, Date, LoadError, LookupResult, Registry, Status};
+
+
+// A sorted range row, `Ipv4Row` or `Ipv6Row`.
+trait Row: Copy + PartialEq {
+    // Rows are grouped into chunks by `first >> SHIFT`.
+    const SHIFT: u32;
+
+    fn bounds(&self) -> (u128, u128);
+}
+
+impl Row for Ipv4Row {
+    // One chunk per /8
+    const SHIFT: u32 = 24;
+
+    #[inline]
+    fn bounds(&self) -> (u128, u128) {
+        (self.0 as u128, self.1 as u128)
+    }
+}
+
+impl Row for Ipv6Row {
+    // One chunk per /16
+    const SHIFT: u32 = 112;
+
+    #[inline]
+    fn bounds(&self) -> (u128, u128) {
+        (self.0, self.1)
+    }
+}
+
+
+/// The table of one snapshot, split into chunks.
+///
+/// A chunk that did not change since the previous snapshot is the same `Arc`,
+/// the history of a table costs about one chunk per change instead of one full
+/// table per day.
+#[derive(Debug, Clone)]
+struct Table<R> {
+    // (first >> SHIFT, rows), sorted by key. A row that spans several chunks is
+    // in each of them.
+    chunks: Vec<(u32, Arc<[R]>)>,
+}
+
+impl<R: Row> Table<R> {
+    fn new<I: Iterator<Item = R>>(rows: I) -> Self {
+        let mut groups: BTreeMap<u32, Vec<R>> = BTreeMap::new();
+        for row in rows {
+            let (first, last) = row.bounds();
+            for key in (first >> R::SHIFT) as u32..=(last >> R::SHIFT) as u32 {
+                groups.entry(key).or_default().push(row);
+            }
+        }
+
+        Table { chunks: groups.into_iter().map(|(key, rows)| (key, Arc::from(rows))).collect() }
+    }
+
+    // Replace the chunks equal to the ones of `other` with theirs.
+    fn share(&mut self, other: &Table<R>) {
+        for (key, chunk) in self.chunks.iter_mut() {
+            if let Some(other_chunk) = other.chunk(*key) {
+                if !Arc::ptr_eq(chunk, other_chunk) && chunk[..] == other_chunk[..] {
+                    *chunk = other_chunk.clone();
+                }
+            }
+        }
+    }
+
+    fn chunk(&self, key: u32) -> Option<&Arc<[R]>> {
+        let pos = self.chunks.binary_search_by_key(&key, |&(key, _)| key).ok()?;
+
+        Some(&self.chunks[pos].1)
+    }
+
+    fn find(&self, number: u128) -> Option<R> {
+        let rows = self.chunk((number >> R::SHIFT) as u32)?;
+        let pos = binary_search(rows.len(), |idx| {
+            let (first, last) = rows[idx].bounds();
+            (number >= first, number <= last)
+        })?;
+
+        Some(rows[pos])
+    }
+}
+
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    date: Date,
+    v4: Table<Ipv4Row>,
+    v6: Table<Ipv6Row>,
+}
+
+impl Snapshot {
+    fn lookup(&self, ip: &IpAddr) -> Option<LookupResult> {
+        match *ip {
+            IpAddr::V4(v4_addr) => {
+                let v4_number = u32::from(v4_addr);
+                self.v4.find(v4_number as u128).map(|row| ipv4_result(v4_number, row))
+            }
+            IpAddr::V6(v6_addr) => {
+                let v6_number = u128::from(v6_addr);
+                self.v6.find(v6_number).map(|row| ipv6_result(v6_number, row))
+            }
+        }
+    }
+}
+
+
+/// A period of `HistoricalDatabase::history`, the address had the same
+/// country, registry and status in every snapshot from `since` to `until`.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct HistoryEntry {
+    since: Date,
+    until: Date,
+    country: Country,
+    registry: Registry,
+    status: Status,
+}
+
+impl HistoryEntry {
+    /// Date of the first snapshot of this period.
+    #[inline]
+    pub fn since(&self) -> Date {
+        self.since
+    }
+
+    /// Date of the last snapshot of this period, included.
+    #[inline]
+    pub fn until(&self) -> Date {
+        self.until
+    }
+
+    #[inline]
+    pub fn country(&self) -> Country {
+        self.country
+    }
+
+    #[inline]
+    pub fn registry(&self) -> Registry {
+        self.registry
+    }
+
+    #[inline]
+    pub fn status(&self) -> Status {
+        self.status
+    }
+}
+
+
+/// A series of dated `Database` snapshots, to look up an address as of a day.
+///
+/// Snapshots share the chunks of their tables that did not change, see
+/// `Table`.
+#[derive(Debug, Clone, Default)]
+pub struct HistoricalDatabase {
+    // Sorted by date
+    snapshots: Vec<Snapshot>,
+}
+
+impl HistoricalDatabase {
+    pub fn new() -> Self {
+        HistoricalDatabase { snapshots: Vec::new() }
+    }
+
+    pub fn from_snapshots<I: IntoIterator<Item = (Date, Database)>>(snapshots: I) -> Self {
+        let mut history = HistoricalDatabase::new();
+        for (date, db) in snapshots {
+            history.insert(date, &db);
+        }
+
+        history
+    }
+
+    /// Load the snapshots in `<data_path>/snapshots/YYYYMMDD`, written by
+    /// `sync --date` and `parse --date`, see `Database::open`.
+    ///
+    /// Snapshot directories without `v4_records` have not been parsed yet and
+    /// are skipped.
+    pub fn open<P: AsRef<Path>>(data_path: P) -> Result<Self, LoadError> {
+        let dir = data_path.as_ref().join("snapshots");
+
+        let mut dates = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if let Some(date) = entry.file_name().to_str().and_then(|name| name.parse::<Date>().ok()) {
+                if entry.path().join("v4_records").is_file() {
+                    dates.push(date);
+                }
+            }
+        }
+        dates.sort();
+
+        let mut history = HistoricalDatabase::new();
+        for date in dates {
+            // NOTE: 逐个加载，同时只保留一份完整的表。
+            let db = Database::open(dir.join(date.to_string()))?;
+            history.insert(date, &db);
+        }
+
+        Ok(history)
+    }
+
+    /// Add the snapshot of `date`, replacing the one of the same day if any.
+    pub fn insert(&mut self, date: Date, db: &Database) {
+        let mut snapshot = Snapshot {
+            date,
+            v4: Table::new((0..db.ipv4_len()).map(|idx| db.ipv4_row(idx))),
+            v6: Table::new((0..db.ipv6_len()).map(|idx| db.ipv6_row(idx))),
+        };
+
+        let pos = self.snapshots.partition_point(|snapshot| snapshot.date < date);
+        if pos > 0 {
+            let prev = &self.snapshots[pos - 1];
+            snapshot.v4.share(&prev.v4);
+            snapshot.v6.share(&prev.v6);
+        }
+
+        match self.snapshots.get(pos) {
+            Some(next) if next.date == date => self.snapshots[pos] = snapshot,
+            _ => self.snapshots.insert(pos, snapshot),
+        }
+
+        // NOTE: 插入到中间时，后一个快照改为共享新快照的块。
+        if let Some((current, rest)) = self.snapshots[pos..].split_first_mut() {
+            if let Some(next) = rest.first_mut() {
+                next.v4.share(&current.v4);
+                next.v6.share(&current.v6);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Dates of the snapshots, in ascending order.
+    pub fn dates(&self) -> impl Iterator<Item = Date> + '_ {
+        self.snapshots.iter().map(|snapshot| snapshot.date)
+    }
+
+    /// Look up `ip` in the newest snapshot on or before `date`.
+    ///
+    /// `None` if `ip` is not in that snapshot, or `date` is before the first one.
+    pub fn lookup_at(&self, ip: &IpAddr, date: Date) -> Option<LookupResult> {
+        let pos = self.snapshots.partition_point(|snapshot| snapshot.date <= date);
+
+        self.snapshots[..pos].last()?.lookup(ip)
+    }
+
+    /// Every country, registry and status `ip` went through, oldest first.
+    ///
+    /// Snapshots in which `ip` is not delegated end a period, and are not in
+    /// any.
+    pub fn history(&self, ip: &IpAddr) -> Vec<HistoryEntry> {
+        let mut entries: Vec<HistoryEntry> = Vec::new();
+        let mut last_found = false;
+
+        for snapshot in self.snapshots.iter() {
+            let ret = match snapshot.lookup(ip) {
+                Some(ret) => ret,
+                None => {
+                    last_found = false;
+                    continue;
+                },
+            };
+
+            match entries.last_mut() {
+                Some(entry) if last_found
+                    && (entry.country, entry.registry, entry.status) == (ret.country(), ret.registry(), ret.status()) => {
+                    entry.until = snapshot.date;
+                },
+                _ => entries.push(HistoryEntry {
+                    since: snapshot.date,
+                    until: snapshot.date,
+                    country: ret.country(),
+                    registry: ret.registry(),
+                    status: ret.status(),
+                }),
+            }
+            last_found = true;
+        }
+
+        entries
+    }
+}
+
+
+#[test]
+fn test_historical_database() {
+    let d1 = Date::new(2026, 1, 1).unwrap();
+    let d2 = Date::new(2026, 2, 1).unwrap();
+    let d3 = Date::new(2026, 3, 1).unwrap();
+    let br = "BR".parse::<Country>().unwrap();
+
+    let db1 = Database::from_records("\
+apnic CN ipv4 1.0.1.0 256 allocated none
+arin US ipv4 8.0.0.0 16777216 allocated none
+", "\
+apnic JP ipv6 2001:218:: 32 allocated none
+").unwrap();
+    let db2 = Database::from_records("\
+apnic CN ipv4 1.0.1.0 256 allocated none
+arin US ipv4 8.0.0.0 16777216 allocated none
+lacnic ZZ ipv4 45.5.0.0 256 available none
+", "").unwrap();
+    let db3 = Database::from_records("\
+apnic HK ipv4 1.0.1.0 256 assigned none
+arin US ipv4 8.0.0.0 16777216 allocated none
+lacnic BR ipv4 45.5.0.0 256 allocated none 20260215
+", "").unwrap();
+
+    // NOTE: 插入顺序不影响结果。
+    let history = HistoricalDatabase::from_snapshots(vec![(d3, db3), (d1, db1), (d2, db2)]);
+    assert_eq!(history.dates().collect::<Vec<Date>>(), vec![d1, d2, d3]);
+
+    let ip = "1.0.1.1".parse::<IpAddr>().unwrap();
+    assert!(history.lookup_at(&ip, Date::new(2025, 12, 31).unwrap()).is_none());
+    assert_eq!(history.lookup_at(&ip, d1).map(|ret| ret.country()), Some(Country::CN));
+    assert_eq!(history.lookup_at(&ip, Date::new(2026, 2, 28).unwrap()).map(|ret| ret.country()), Some(Country::CN));
+    assert_eq!(history.lookup_at(&ip, Date::new(2030, 1, 1).unwrap()).map(|ret| ret.country()), Some(Country::HK));
+
+    let ret = history.lookup_at(&"45.5.0.1".parse().unwrap(), d3).unwrap();
+    assert_eq!((ret.country(), ret.status(), ret.date()), (br, Status::Allocated, Date::new(2026, 2, 15).ok()));
+    assert!(history.lookup_at(&"2001:218::1".parse().unwrap(), d2).is_none());
+
+    let entries = history.history(&"45.5.0.1".parse().unwrap());
+    assert_eq!(entries.iter().map(|e| (e.since(), e.until(), e.country(), e.status())).collect::<Vec<_>>(),
+               vec![(d2, d2, Country::ZZ, Status::Available), (d3, d3, br, Status::Allocated)]);
+
+    let entries = history.history(&"8.8.8.8".parse().unwrap());
+    assert_eq!(entries.len(), 1);
+    assert_eq!((entries[0].since(), entries[0].until(), entries[0].registry()), (d1, d3, Registry::Arin));
+
+    let entries = history.history(&"2001:218::1".parse().unwrap());
+    assert_eq!(entries.iter().map(|e| (e.since(), e.until())).collect::<Vec<_>>(), vec![(d1, d1)]);
+    assert!(history.history(&"9.9.9.9".parse().unwrap()).is_empty());
+
+    // 8.0.0.0/8 never changed, 1.0.0.0/8 changed on d3.
+    let chunk = |idx: usize, key: u32| history.snapshots[idx].v4.chunk(key).unwrap().clone();
+    assert!(Arc::ptr_eq(&chunk(0, 8), &chunk(1, 8)) && Arc::ptr_eq(&chunk(1, 8), &chunk(2, 8)));
+    assert!(Arc::ptr_eq(&chunk(0, 1), &chunk(1, 1)));
+    assert!(!Arc::ptr_eq(&chunk(1, 1), &chunk(2, 1)));
+}
+
+#[test]
+fn test_historical_database_spanning_rows() {
+    let d1 = Date::new(2026, 1, 1).unwrap();
+    let db = Database::from_records("arin US ipv4 3.0.0.0 33554432 allocated none", "\
+ripencc ZZ ipv6 2a10:: 15 available none
+").unwrap();
+
+    let mut history = HistoricalDatabase::new();
+    history.insert(d1, &db);
+    history.insert(d1, &db);
+    assert_eq!(history.len(), 1);
+
+    for &ip in ["3.0.0.0", "3.255.255.255", "4.0.0.0", "4.255.255.255", "2a10::1", "2a11:ffff::1"].iter() {
+        let ip = ip.parse::<IpAddr>().unwrap();
+        assert_eq!(history.lookup_at(&ip, d1), db.lookup(&ip), "{}", ip);
+    }
+    assert!(history.lookup_at(&"5.0.0.0".parse().unwrap(), d1).is_none());
+    assert!(history.lookup_at(&"2a12::1".parse().unwrap(), d1).is_none());
+}
@@ -1,65 +1,158 @@
-use std::cmp::Ordering;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::IpAddr;
+#[cfg(test)]
+use std::net::Ipv4Addr;
 
+mod asn;
 mod country;
+mod database;
+mod date;
+mod format;
+mod history;
+mod net;
+mod registry;
+mod shared;
+mod special;
+mod status;
+#[allow(dead_code)]
+mod asn_db;
 #[allow(dead_code)]
 mod v4_db;
 #[allow(dead_code)]
 mod v6_db;
 
-pub use self::country::Country;
-
-
-pub fn lookup(ip: &IpAddr) -> Option<(IpAddr, IpAddr, Country)> {
-    match ip {
-        &IpAddr::V4(v4_addr) => {
-            let v4_number = u32::from(v4_addr);
-            let ret = v4_db::IPV4_RECORDS.binary_search_by(|&(first, last, _cc)| {
-                if v4_number >= last {
-                    Ordering::Less
-                } else if v4_number >= first && v4_number <= last {
-                    Ordering::Equal
-                } else if v4_number < first {
-                    Ordering::Greater
-                } else {
-                    unreachable!()
-                }
-            });
-            match ret {
-                Ok(pos) => {
-                    let (first, last, cc) = v4_db::IPV4_RECORDS[pos];
-                    Some( (IpAddr::from(Ipv4Addr::from(first)),
-                           IpAddr::from(Ipv4Addr::from(last)),
-                           Country::from_index(cc)) )
-                }
-                Err(_) => None
-            }
-        }
-        &IpAddr::V6(v6_addr) => {
-            let v6_number = u128::from(v6_addr);
-            let ret = v6_db::IPV6_RECORDS.binary_search_by(|&(first, last, _cc)| {
-                if v6_number >= last {
-                    Ordering::Less
-                } else if v6_number >= first && v6_number <= last {
-                    Ordering::Equal
-                } else if v6_number < first {
-                    Ordering::Greater
-                } else {
-                    unreachable!()
-                }
-            });
-
-            match ret {
-                Ok(pos) => {
-                    let (first, last, cc) = v6_db::IPV6_RECORDS[pos];
-                    Some( (IpAddr::from(Ipv6Addr::from(first)),
-                           IpAddr::from(Ipv6Addr::from(last)),
-                           Country::from_index(cc) ))
-                }
-                Err(_) => None
-            }
-        }
+pub use self::asn::AsnLookupResult;
+pub use self::country::{Country, InvalidCountryCode};
+pub use self::database::{Database, LoadError, Ranges, Cidrs};
+pub use self::date::{Date, InvalidDate};
+pub use self::format::FormatError;
+pub use self::history::{HistoricalDatabase, HistoryEntry};
+pub use self::net::{IpNet, IpNetIter};
+pub use self::registry::{Registry, InvalidRegistry};
+pub use self::shared::{SharedDatabase, Watcher};
+pub use self::special::SpecialPurpose;
+pub use self::status::{Status, StatusFilter, InvalidStatus};
+
+
+/// A delegated block that contains the looked up address.
+///
+/// `parse` merges adjacent blocks of the same country by default
+/// (`--merge country`). The country is exact, but the bounds are those of the
+/// merged block, the registry and status those of its first RIR record and
+/// the date is `None` unless every merged record has the same one. Build the
+/// tables with `--merge strict` or `--merge split` for exact fields.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct LookupResult {
+    first: IpAddr,
+    last: IpAddr,
+    network: IpNet,
+    country: Country,
+    registry: Registry,
+    status: Status,
+    date: Option<Date>,
+}
+
+impl LookupResult {
+    /// First address of the delegated block.
+    #[inline]
+    pub fn first(&self) -> IpAddr {
+        self.first
+    }
+
+    /// Last address of the delegated block.
+    #[inline]
+    pub fn last(&self) -> IpAddr {
+        self.last
+    }
+
+    /// The largest prefix inside the delegated block that contains the address.
+    /// 
+    /// IPv4 delegations are counted by addresses and may not be a single prefix.
+    #[inline]
+    pub fn network(&self) -> IpNet {
+        self.network
+    }
+
+    #[inline]
+    pub fn country(&self) -> Country {
+        self.country
+    }
+
+    /// The RIR which delegated this block.
+    #[inline]
+    pub fn registry(&self) -> Registry {
+        self.registry
     }
+
+    #[inline]
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Allocation date, `None` if the RIR does not publish one.
+    #[inline]
+    pub fn date(&self) -> Option<Date> {
+        self.date
+    }
+}
+
+/// Result of `classify`.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Classified {
+    /// A special-purpose address, with the most specific registry block that
+    /// contains it.
+    Special(SpecialPurpose, IpNet),
+    /// An address delegated by a RIR.
+    Delegated(LookupResult),
+}
+
+/// Look up `ip` in the builtin tables, see `Database::builtin()`.
+#[inline]
+pub fn lookup(ip: &IpAddr) -> Option<LookupResult> {
+    Database::builtin().lookup(ip)
+}
+
+/// Look up `ip` in the builtin tables, ignoring blocks whose status is not in
+/// `filter`, see `Database::lookup_filtered`.
+#[inline]
+pub fn lookup_filtered(ip: &IpAddr, filter: StatusFilter) -> Option<LookupResult> {
+    Database::builtin().lookup_filtered(ip, filter)
+}
+
+/// The RIR responsible for `ip` in the builtin tables, see
+/// `Database::lookup_registry`.
+#[inline]
+pub fn lookup_registry(ip: &IpAddr) -> Option<Registry> {
+    Database::builtin().lookup_registry(ip)
+}
+
+/// Classify `ip` as special-purpose or delegated, see `Database::classify`.
+#[inline]
+pub fn classify(ip: &IpAddr) -> Option<Classified> {
+    Database::builtin().classify(ip)
+}
+
+/// The IANA special-purpose block kind of `ip`, e.g. private or loopback.
+#[inline]
+pub fn special_purpose(ip: &IpAddr) -> Option<SpecialPurpose> {
+    special::lookup(ip).map(|(kind, _net)| kind)
+}
+
+/// Look up the AS number `asn` in the builtin `asn` table.
+#[inline]
+pub fn lookup_asn(asn: u32) -> Option<AsnLookupResult> {
+    asn::lookup(&asn_db::ASN_RECORDS, asn)
+}
+
+/// Every range delegated to `country` in the builtin tables.
+#[inline]
+pub fn ranges_for(country: Country) -> Ranges<'static> {
+    Database::builtin().ranges_for(country)
+}
+
+/// The minimal list of prefixes covering `country` in the builtin tables.
+#[inline]
+pub fn cidrs_for(country: Country) -> Cidrs<'static> {
+    Database::builtin().cidrs_for(country)
 }
 
 
@@ -71,4 +164,46 @@ fn test_lookup_ipv4() {
 #[test]
 fn test_lookup_ipv6() {
     assert_eq!(lookup(&"2001:218::".parse().unwrap()).is_some(), true);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_lookup_result() {
+    let ret = lookup(&IpAddr::from(Ipv4Addr::new(8, 8, 8, 8))).unwrap();
+    assert_eq!(ret.country(), Country::US);
+    assert_eq!(ret.registry(), Registry::Arin);
+    assert!(ret.network().contains(&IpAddr::from(Ipv4Addr::new(8, 8, 8, 8))));
+}
+
+#[test]
+fn test_lookup_asn() {
+    // AS15169, Google
+    let ret = lookup_asn(15169).unwrap();
+    assert_eq!(ret.country(), Country::US);
+    assert_eq!(ret.registry(), Registry::Arin);
+    assert!(ret.first() <= 15169 && 15169 <= ret.last());
+}
+
+#[test]
+fn test_lookup_registry() {
+    assert_eq!(lookup_registry(&IpAddr::from(Ipv4Addr::new(8, 8, 8, 8))), Some(Registry::Arin));
+    assert_eq!(lookup_registry(&IpAddr::from(Ipv4Addr::new(1, 0, 0, 1))), Some(Registry::Apnic));
+}
+
+#[test]
+fn test_classify() {
+    match classify(&IpAddr::from(Ipv4Addr::new(192, 168, 1, 1))) {
+        Some(Classified::Special(kind, net)) => {
+            assert_eq!(kind, SpecialPurpose::Private);
+            assert_eq!(net.to_string(), "192.168.0.0/16");
+        },
+        ret => panic!("{:?}", ret),
+    }
+
+    match classify(&IpAddr::from(Ipv4Addr::new(8, 8, 8, 8))) {
+        Some(Classified::Delegated(ret)) => assert_eq!(ret.country(), Country::US),
+        ret => panic!("{:?}", ret),
+    }
+
+    assert_eq!(special_purpose(&"::1".parse().unwrap()), Some(SpecialPurpose::Loopback));
+    assert_eq!(special_purpose(&IpAddr::from(Ipv4Addr::new(8, 8, 8, 8))), None);
+}
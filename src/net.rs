@@ -0,0 +1,214 @@
+use std::fmt;
+use std::cmp;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+
+/// An IP network prefix, e.g. `1.0.1.0/24` or `2001:218::/32`.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    /// Host bits of `addr` are cleared.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<Self> {
+        match addr {
+            IpAddr::V4(v4_addr) => {
+                if prefix_len > 32 {
+                    return None;
+                }
+                let network = u32::from(v4_addr) & v4_mask(prefix_len);
+                Some(IpNet { addr: IpAddr::from(Ipv4Addr::from(network)), prefix_len })
+            }
+            IpAddr::V6(v6_addr) => {
+                if prefix_len > 128 {
+                    return None;
+                }
+                let network = u128::from(v6_addr) & v6_mask(prefix_len);
+                Some(IpNet { addr: IpAddr::from(Ipv6Addr::from(network)), prefix_len })
+            }
+        }
+    }
+
+    #[inline]
+    pub fn network(&self) -> IpAddr {
+        self.addr
+    }
+
+    #[inline]
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// The last address of this prefix.
+    pub fn last(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(v4_addr) => {
+                IpAddr::from(Ipv4Addr::from(u32::from(v4_addr) | !v4_mask(self.prefix_len)))
+            }
+            IpAddr::V6(v6_addr) => {
+                IpAddr::from(Ipv6Addr::from(u128::from(v6_addr) | !v6_mask(self.prefix_len)))
+            }
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(network), IpAddr::V4(v4_addr)) => {
+                u32::from(*v4_addr) & v4_mask(self.prefix_len) == u32::from(network)
+            }
+            (IpAddr::V6(network), IpAddr::V6(v6_addr)) => {
+                u128::from(*v6_addr) & v6_mask(self.prefix_len) == u128::from(network)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+
+#[inline]
+pub(crate) fn v4_mask(prefix_len: u8) -> u32 {
+    if prefix_len == 0 { 0 } else { u32::MAX << (32 - prefix_len as u32) }
+}
+
+#[inline]
+pub(crate) fn v6_mask(prefix_len: u8) -> u128 {
+    if prefix_len == 0 { 0 } else { u128::MAX << (128 - prefix_len as u32) }
+}
+
+/// The largest prefix that contains `number` and fits inside `first ..= last`.
+pub(crate) fn v4_enclosing_net(number: u32, first: u32, last: u32) -> IpNet {
+    for prefix_len in 0..=32u8 {
+        let mask = v4_mask(prefix_len);
+        let network = number & mask;
+        if network >= first && network | !mask <= last {
+            return IpNet { addr: IpAddr::from(Ipv4Addr::from(network)), prefix_len };
+        }
+    }
+
+    unreachable!()
+}
+
+/// The largest prefix that contains `number` and fits inside `first ..= last`.
+pub(crate) fn v6_enclosing_net(number: u128, first: u128, last: u128) -> IpNet {
+    for prefix_len in 0..=128u8 {
+        let mask = v6_mask(prefix_len);
+        let network = number & mask;
+        if network >= first && network | !mask <= last {
+            return IpNet { addr: IpAddr::from(Ipv6Addr::from(network)), prefix_len };
+        }
+    }
+
+    unreachable!()
+}
+
+
+/// The minimal list of prefixes that exactly cover `first ..= last`.
+#[derive(Debug, Clone)]
+pub struct IpNetIter {
+    start: u128,
+    end: u128,
+    // 32 or 128
+    width: u32,
+    done: bool,
+}
+
+impl IpNetIter {
+    /// # Panics
+    /// 
+    /// Panics if `first` and `last` are not of the same address family.
+    pub fn new(first: IpAddr, last: IpAddr) -> Self {
+        match (first, last) {
+            (IpAddr::V4(first), IpAddr::V4(last)) => {
+                let (start, end) = (u32::from(first) as u128, u32::from(last) as u128);
+                IpNetIter { start, end, width: 32, done: start > end }
+            }
+            (IpAddr::V6(first), IpAddr::V6(last)) => {
+                let (start, end) = (u128::from(first), u128::from(last));
+                IpNetIter { start, end, width: 128, done: start > end }
+            }
+            _ => panic!("address family mismatch: {} - {}", first, last),
+        }
+    }
+}
+
+impl Iterator for IpNetIter {
+    type Item = IpNet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        // 对齐位数与剩余地址数量，两者取较小值即为当前前缀的主机位数。
+        let align = if self.start == 0 { self.width } else { cmp::min(self.start.trailing_zeros(), self.width) };
+        let span = self.end - self.start;
+        let fits = if span == u128::MAX { 128 } else { 127 - (span + 1).leading_zeros() };
+        let host_bits = cmp::min(align, fits);
+
+        let addr = if self.width == 32 {
+            IpAddr::from(Ipv4Addr::from(self.start as u32))
+        } else {
+            IpAddr::from(Ipv6Addr::from(self.start))
+        };
+        let net = IpNet { addr, prefix_len: (self.width - host_bits) as u8 };
+
+        let size_minus_one = if host_bits == 128 { u128::MAX } else { (1u128 << host_bits) - 1 };
+        if self.start + size_minus_one == self.end {
+            self.done = true;
+        } else {
+            self.start += size_minus_one + 1;
+        }
+
+        Some(net)
+    }
+}
+
+
+#[test]
+fn test_enclosing_net() {
+    // 1.0.2.0 - 1.0.3.255
+    let first = u32::from(Ipv4Addr::new(1, 0, 2, 0));
+    let last  = u32::from(Ipv4Addr::new(1, 0, 3, 255));
+    let net = v4_enclosing_net(u32::from(Ipv4Addr::new(1, 0, 3, 7)), first, last);
+    assert_eq!(format!("{}", net), "1.0.2.0/23");
+
+    // 1.0.1.0 - 1.0.3.255, not aligned on a single prefix.
+    let first = u32::from(Ipv4Addr::new(1, 0, 1, 0));
+    let net = v4_enclosing_net(u32::from(Ipv4Addr::new(1, 0, 1, 9)), first, last);
+    assert_eq!(format!("{}", net), "1.0.1.0/24");
+    assert!(net.contains(&IpAddr::from(Ipv4Addr::new(1, 0, 1, 255))));
+    assert_eq!(net.last(), IpAddr::from(Ipv4Addr::new(1, 0, 1, 255)));
+
+    let net = v6_enclosing_net(1, 0, u128::MAX);
+    assert_eq!(format!("{}", net), "::/0");
+}
+
+#[test]
+fn test_ip_net_iter() {
+    let cidrs = |first: &str, last: &str| -> Vec<String> {
+        IpNetIter::new(first.parse().unwrap(), last.parse().unwrap()).map(|net| net.to_string()).collect()
+    };
+
+    assert_eq!(cidrs("1.0.1.0", "1.0.3.255"), vec!["1.0.1.0/24", "1.0.2.0/23"]);
+    assert_eq!(cidrs("5.42.240.0", "5.42.255.255"), vec!["5.42.240.0/20"]);
+    assert_eq!(cidrs("10.0.0.1", "10.0.0.1"), vec!["10.0.0.1/32"]);
+    assert_eq!(cidrs("0.0.0.0", "255.255.255.255"), vec!["0.0.0.0/0"]);
+    assert_eq!(cidrs("255.255.255.254", "255.255.255.255"), vec!["255.255.255.254/31"]);
+    assert_eq!(cidrs("::", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), vec!["::/0"]);
+    assert_eq!(cidrs("2001:218::", "2001:219:ffff:ffff:ffff:ffff:ffff:ffff"), vec!["2001:218::/31"]);
+    assert!(cidrs("1.0.0.1", "1.0.0.0").is_empty());
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::str::FromStr;
+use std::convert::TryFrom;
 
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
@@ -19,7 +20,7 @@ impl fmt::Display for InvalidRegistry {
 /// 
 /// `https://www.iana.org/numbers`
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Registry {
     /// Africa Region
     Afrinic = 0u8,
@@ -38,7 +39,17 @@ pub enum Registry {
 }
 
 impl Registry {
-    pub fn from_index(index: u8) -> Result<Self, ()> {
+    pub const ALL: [Registry; 7] = [
+        Registry::Afrinic,
+        Registry::Apnic,
+        Registry::Arin,
+        Registry::Iana,
+        Registry::Ietf,
+        Registry::Lacnic,
+        Registry::Ripencc,
+    ];
+
+    pub fn from_index(index: u8) -> Result<Self, InvalidRegistry> {
         match index {
             0u8 => Ok(Registry::Afrinic),
             1u8 => Ok(Registry::Apnic),
@@ -47,7 +58,7 @@ impl Registry {
             4u8 => Ok(Registry::Ietf),
             5u8 => Ok(Registry::Lacnic),
             6u8 => Ok(Registry::Ripencc),
-            _ => Err(())
+            _ => Err(InvalidRegistry)
         }
     }
 
@@ -76,6 +87,20 @@ impl Registry {
     }
 }
 
+impl From<Registry> for u8 {
+    fn from(registry: Registry) -> u8 {
+        registry.index()
+    }
+}
+
+impl TryFrom<u8> for Registry {
+    type Error = InvalidRegistry;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        Registry::from_index(index)
+    }
+}
+
 impl FromStr for Registry {
     type Err = InvalidRegistry;
 
@@ -106,3 +131,16 @@ impl fmt::Display for Registry {
         }
     }
 }
+
+
+#[test]
+fn test_registry_round_trip() {
+    for registry in Registry::ALL.iter() {
+        assert_eq!(Registry::from_index(registry.index()), Ok(*registry));
+        assert_eq!(Registry::try_from(u8::from(*registry)), Ok(*registry));
+        assert_eq!(format!("{}", registry).parse::<Registry>(), Ok(*registry));
+    }
+
+    assert_eq!(Registry::from_index(Registry::ALL.len() as u8), Err(InvalidRegistry));
+    assert_eq!("RIPE".parse::<Registry>(), Err(InvalidRegistry));
+}
@@ -0,0 +1,176 @@
+use arc_swap::ArcSwap;
+
+use std::fs;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use std::path::{Path, PathBuf};
+use std::net::IpAddr;
+
+use crate::{Database, LoadError, LookupResult, Registry};
+
+
+/// A `Database` handle that can be replaced while other threads keep looking up.
+///
+/// Readers never block: `lookup` works on whichever database was current when it
+/// started, and `store` swaps in the new one atomically.
+#[derive(Debug, Clone)]
+pub struct SharedDatabase {
+    inner: Arc<ArcSwap<Database>>,
+}
+
+impl SharedDatabase {
+    pub fn new(db: Database) -> Self {
+        SharedDatabase { inner: Arc::new(ArcSwap::from_pointee(db)) }
+    }
+
+    /// Open a binary database file, see `Database::open_binary`.
+    pub fn open_binary<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        Ok(SharedDatabase::new(Database::open_binary(path)?))
+    }
+
+    #[inline]
+    pub fn lookup(&self, ip: &IpAddr) -> Option<LookupResult> {
+        self.inner.load().lookup(ip)
+    }
+
+    #[inline]
+    pub fn lookup_registry(&self, ip: &IpAddr) -> Option<Registry> {
+        self.inner.load().lookup_registry(ip)
+    }
+
+    /// The current database.
+    pub fn snapshot(&self) -> Arc<Database> {
+        self.inner.load_full()
+    }
+
+    /// Replace the current database.
+    pub fn store(&self, db: Database) {
+        self.inner.store(Arc::new(db));
+    }
+
+    /// Load `path` with `Database::open_binary` and replace the current database.
+    ///
+    /// On error the current database is kept.
+    pub fn reload_binary<P: AsRef<Path>>(&self, path: P) -> Result<(), LoadError> {
+        let db = Database::open_binary(path)?;
+        self.store(db);
+
+        Ok(())
+    }
+
+    /// Poll the binary database file at `path` every `interval`, and reload it
+    /// when its modification time or size changes.
+    ///
+    /// A file that fails to load (e.g. still being written) is retried on the
+    /// next poll. Polling stops when the returned `Watcher` is dropped.
+    pub fn watch_binary<P: AsRef<Path>>(&self, path: P, interval: Duration) -> Watcher {
+        let path = path.as_ref().to_path_buf();
+        let watched_path = path.clone();
+        let shared = self.clone();
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let mut last_stamp = file_stamp(&path);
+
+        let handle = thread::spawn(move || {
+            // NOTE: Sender 被 drop 之后 recv_timeout 返回 Disconnected，线程退出。
+            while let Err(mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(interval) {
+                let stamp = file_stamp(&path);
+                if stamp.is_none() || stamp == last_stamp {
+                    continue;
+                }
+
+                if shared.reload_binary(&path).is_ok() {
+                    last_stamp = stamp;
+                }
+            }
+        });
+
+        Watcher { path: watched_path, stop: Some(tx), handle: Some(handle) }
+    }
+}
+
+impl From<Database> for SharedDatabase {
+    fn from(db: Database) -> Self {
+        SharedDatabase::new(db)
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+
+/// Background reloader returned by `SharedDatabase::watch_binary`.
+#[derive(Debug)]
+pub struct Watcher {
+    path: PathBuf,
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+
+#[test]
+fn test_shared_database_store() {
+    let ip = "1.0.1.1".parse::<IpAddr>().unwrap();
+
+    let shared = SharedDatabase::new(Database::from_records("", "").unwrap());
+    let reader = shared.clone();
+    assert!(reader.lookup(&ip).is_none());
+
+    let db = Database::from_records("apnic CN ipv4 1.0.1.0 256 allocated none", "").unwrap();
+    shared.store(db);
+    assert_eq!(reader.lookup(&ip).map(|ret| ret.country()), Some(crate::Country::CN));
+}
+
+#[test]
+fn test_shared_database_watch() {
+    let ip = "1.0.1.1".parse::<IpAddr>().unwrap();
+    let path = std::env::temp_dir().join(format!("iana-ip-db-watch-{}.db", std::process::id()));
+
+    let write = |records: &str| {
+        let mut buf = Vec::new();
+        Database::from_records(records, "").unwrap().write_binary(&mut buf).unwrap();
+        // NOTE: 先写临时文件再重命名，映射中的旧文件不会被修改。
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, &buf).unwrap();
+        fs::rename(&tmp_path, &path).unwrap();
+    };
+
+    write("");
+    let shared = SharedDatabase::open_binary(&path).unwrap();
+    let watcher = shared.watch_binary(&path, Duration::from_millis(10));
+    assert!(shared.lookup(&ip).is_none());
+
+    write("apnic CN ipv4 1.0.1.0 256 allocated none");
+    let mut found = false;
+    for _ in 0..500 {
+        if shared.lookup(&ip).is_some() {
+            found = true;
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    drop(watcher);
+    let _ = fs::remove_file(&path);
+    assert!(found);
+}
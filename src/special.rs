@@ -0,0 +1,207 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::net::{IpNet, v4_mask, v6_mask};
+
+
+/// Kind of a block in the IANA special-purpose address registries
+///
+/// `https://www.iana.org/assignments/iana-ipv4-special-registry/`
+/// `https://www.iana.org/assignments/iana-ipv6-special-registry/`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpecialPurpose {
+    /// `0.0.0.0/32`, `::/128`
+    Unspecified,
+    /// `0.0.0.0/8`, "this network"
+    ThisNetwork,
+    /// RFC 1918
+    Private,
+    /// `100.64.0.0/10`, carrier-grade NAT
+    Shared,
+    Loopback,
+    LinkLocal,
+    /// `192.0.0.0/24`, `2001::/23`
+    IetfProtocol,
+    /// TEST-NET-1/2/3, MCAST-TEST-NET, `2001:db8::/32`, `3fff::/20`
+    Documentation,
+    /// `198.18.0.0/15`, `2001:2::/48`
+    Benchmarking,
+    Multicast,
+    /// `240.0.0.0/4`
+    Reserved,
+    /// `255.255.255.255/32`
+    Broadcast,
+    /// `::ffff:0:0/96`
+    Ipv4Mapped,
+    /// `64:ff9b::/96`, `64:ff9b:1::/48`
+    Translation,
+    /// `100::/64`
+    DiscardOnly,
+    /// `2001::/32`
+    Teredo,
+    /// `192.88.99.0/24`, `2002::/16`
+    Relay6to4,
+    As112,
+    /// Automatic Multicast Tunneling
+    Amt,
+    /// `2001:20::/28`, ORCHIDv2
+    Orchid,
+    /// `fc00::/7`
+    UniqueLocal,
+}
+
+impl SpecialPurpose {
+    /// Not globally reachable, traffic from these blocks should not show up
+    /// on the public internet.
+    ///
+    /// Anycast and transition blocks (`As112`, `Amt`, `Teredo`, `Relay6to4`,
+    /// `Translation`) are reachable and are not bogons.
+    pub fn is_bogon(&self) -> bool {
+        !matches!(*self,
+            SpecialPurpose::Teredo
+            | SpecialPurpose::Relay6to4
+            | SpecialPurpose::As112
+            | SpecialPurpose::Amt
+            | SpecialPurpose::Translation)
+    }
+}
+
+impl fmt::Display for SpecialPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpecialPurpose::Unspecified   => write!(f, "unspecified"),
+            SpecialPurpose::ThisNetwork   => write!(f, "this-network"),
+            SpecialPurpose::Private       => write!(f, "private"),
+            SpecialPurpose::Shared        => write!(f, "shared"),
+            SpecialPurpose::Loopback      => write!(f, "loopback"),
+            SpecialPurpose::LinkLocal     => write!(f, "link-local"),
+            SpecialPurpose::IetfProtocol  => write!(f, "ietf-protocol"),
+            SpecialPurpose::Documentation => write!(f, "documentation"),
+            SpecialPurpose::Benchmarking  => write!(f, "benchmarking"),
+            SpecialPurpose::Multicast     => write!(f, "multicast"),
+            SpecialPurpose::Reserved      => write!(f, "reserved"),
+            SpecialPurpose::Broadcast     => write!(f, "broadcast"),
+            SpecialPurpose::Ipv4Mapped    => write!(f, "ipv4-mapped"),
+            SpecialPurpose::Translation   => write!(f, "translation"),
+            SpecialPurpose::DiscardOnly   => write!(f, "discard-only"),
+            SpecialPurpose::Teredo        => write!(f, "teredo"),
+            SpecialPurpose::Relay6to4     => write!(f, "6to4"),
+            SpecialPurpose::As112         => write!(f, "as112"),
+            SpecialPurpose::Amt           => write!(f, "amt"),
+            SpecialPurpose::Orchid        => write!(f, "orchid"),
+            SpecialPurpose::UniqueLocal   => write!(f, "unique-local"),
+        }
+    }
+}
+
+
+// Format: (network, prefix_len, kind)
+//
+// Blocks may nest, e.g. `2001::/32` inside `2001::/23`, the longest prefix wins.
+// Protocol addresses inside a block of the same kind are not listed.
+pub(crate) static SPECIAL_V4: [(u32, u8, SpecialPurpose); 21] = [
+    (0x0000_0000,  8, SpecialPurpose::ThisNetwork),
+    (0x0000_0000, 32, SpecialPurpose::Unspecified),
+    (0x0a00_0000,  8, SpecialPurpose::Private),
+    (0x6440_0000, 10, SpecialPurpose::Shared),
+    (0x7f00_0000,  8, SpecialPurpose::Loopback),
+    (0xa9fe_0000, 16, SpecialPurpose::LinkLocal),
+    (0xac10_0000, 12, SpecialPurpose::Private),
+    (0xc000_0000, 24, SpecialPurpose::IetfProtocol),
+    (0xc000_0200, 24, SpecialPurpose::Documentation),
+    (0xc01f_c400, 24, SpecialPurpose::As112),
+    (0xc034_c100, 24, SpecialPurpose::Amt),
+    (0xc058_6300, 24, SpecialPurpose::Relay6to4),
+    (0xc0a8_0000, 16, SpecialPurpose::Private),
+    (0xc0af_3000, 24, SpecialPurpose::As112),
+    (0xc612_0000, 15, SpecialPurpose::Benchmarking),
+    (0xc633_6400, 24, SpecialPurpose::Documentation),
+    (0xcb00_7100, 24, SpecialPurpose::Documentation),
+    (0xe000_0000,  4, SpecialPurpose::Multicast),
+    (0xe9fc_0000, 24, SpecialPurpose::Documentation),
+    (0xf000_0000,  4, SpecialPurpose::Reserved),
+    (0xffff_ffff, 32, SpecialPurpose::Broadcast),
+];
+
+pub(crate) static SPECIAL_V6: [(u128, u8, SpecialPurpose); 19] = [
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 128, SpecialPurpose::Unspecified),
+    (0x0000_0000_0000_0000_0000_0000_0000_0001, 128, SpecialPurpose::Loopback),
+    (0x0000_0000_0000_0000_0000_ffff_0000_0000,  96, SpecialPurpose::Ipv4Mapped),
+    (0x0064_ff9b_0000_0000_0000_0000_0000_0000,  96, SpecialPurpose::Translation),
+    (0x0064_ff9b_0001_0000_0000_0000_0000_0000,  48, SpecialPurpose::Translation),
+    (0x0100_0000_0000_0000_0000_0000_0000_0000,  64, SpecialPurpose::DiscardOnly),
+    (0x2001_0000_0000_0000_0000_0000_0000_0000,  23, SpecialPurpose::IetfProtocol),
+    (0x2001_0000_0000_0000_0000_0000_0000_0000,  32, SpecialPurpose::Teredo),
+    (0x2001_0002_0000_0000_0000_0000_0000_0000,  48, SpecialPurpose::Benchmarking),
+    (0x2001_0003_0000_0000_0000_0000_0000_0000,  32, SpecialPurpose::Amt),
+    (0x2001_0004_0112_0000_0000_0000_0000_0000,  48, SpecialPurpose::As112),
+    (0x2001_0020_0000_0000_0000_0000_0000_0000,  28, SpecialPurpose::Orchid),
+    (0x2001_0db8_0000_0000_0000_0000_0000_0000,  32, SpecialPurpose::Documentation),
+    (0x2002_0000_0000_0000_0000_0000_0000_0000,  16, SpecialPurpose::Relay6to4),
+    (0x2620_004f_8000_0000_0000_0000_0000_0000,  48, SpecialPurpose::As112),
+    (0x3fff_0000_0000_0000_0000_0000_0000_0000,  20, SpecialPurpose::Documentation),
+    (0xfc00_0000_0000_0000_0000_0000_0000_0000,   7, SpecialPurpose::UniqueLocal),
+    (0xfe80_0000_0000_0000_0000_0000_0000_0000,  10, SpecialPurpose::LinkLocal),
+    (0xff00_0000_0000_0000_0000_0000_0000_0000,   8, SpecialPurpose::Multicast),
+];
+
+
+/// The most specific special-purpose block that contains `ip`.
+pub(crate) fn lookup(ip: &IpAddr) -> Option<(SpecialPurpose, IpNet)> {
+    // NOTE: 表很小，线性扫描取最长前缀即可。
+    match *ip {
+        IpAddr::V4(v4_addr) => {
+            let number = u32::from(v4_addr);
+            SPECIAL_V4.iter()
+                .filter(|&&(network, prefix_len, _)| number & v4_mask(prefix_len) == network)
+                .max_by_key(|&&(_, prefix_len, _)| prefix_len)
+                .map(|&(network, prefix_len, kind)| {
+                    (kind, IpNet::new(IpAddr::from(Ipv4Addr::from(network)), prefix_len).unwrap())
+                })
+        }
+        IpAddr::V6(v6_addr) => {
+            let number = u128::from(v6_addr);
+            SPECIAL_V6.iter()
+                .filter(|&&(network, prefix_len, _)| number & v6_mask(prefix_len) == network)
+                .max_by_key(|&&(_, prefix_len, _)| prefix_len)
+                .map(|&(network, prefix_len, kind)| {
+                    (kind, IpNet::new(IpAddr::from(Ipv6Addr::from(network)), prefix_len).unwrap())
+                })
+        }
+    }
+}
+
+
+#[test]
+fn test_special_purpose_lookup() {
+    let kind = |ip: &str| lookup(&ip.parse().unwrap()).map(|(kind, net)| (kind, net.to_string()));
+
+    assert_eq!(kind("10.1.2.3"), Some((SpecialPurpose::Private, "10.0.0.0/8".to_string())));
+    assert_eq!(kind("172.31.255.255"), Some((SpecialPurpose::Private, "172.16.0.0/12".to_string())));
+    assert_eq!(kind("100.100.0.1"), Some((SpecialPurpose::Shared, "100.64.0.0/10".to_string())));
+    assert_eq!(kind("127.0.0.1"), Some((SpecialPurpose::Loopback, "127.0.0.0/8".to_string())));
+    assert_eq!(kind("0.0.0.0"), Some((SpecialPurpose::Unspecified, "0.0.0.0/32".to_string())));
+    assert_eq!(kind("0.1.2.3"), Some((SpecialPurpose::ThisNetwork, "0.0.0.0/8".to_string())));
+    assert_eq!(kind("198.19.0.1"), Some((SpecialPurpose::Benchmarking, "198.18.0.0/15".to_string())));
+    assert_eq!(kind("203.0.113.9"), Some((SpecialPurpose::Documentation, "203.0.113.0/24".to_string())));
+    assert_eq!(kind("233.252.0.1"), Some((SpecialPurpose::Documentation, "233.252.0.0/24".to_string())));
+    assert_eq!(kind("239.1.1.1"), Some((SpecialPurpose::Multicast, "224.0.0.0/4".to_string())));
+    assert_eq!(kind("255.255.255.255"), Some((SpecialPurpose::Broadcast, "255.255.255.255/32".to_string())));
+    assert_eq!(kind("255.255.255.254"), Some((SpecialPurpose::Reserved, "240.0.0.0/4".to_string())));
+    assert_eq!(kind("8.8.8.8"), None);
+    assert_eq!(kind("172.32.0.1"), None);
+
+    assert_eq!(kind("::"), Some((SpecialPurpose::Unspecified, "::/128".to_string())));
+    assert_eq!(kind("::1"), Some((SpecialPurpose::Loopback, "::1/128".to_string())));
+    assert_eq!(kind("::ffff:8.8.8.8").map(|ret| ret.0), Some(SpecialPurpose::Ipv4Mapped));
+    assert_eq!(kind("fd00::1").map(|ret| ret.0), Some(SpecialPurpose::UniqueLocal));
+    assert_eq!(kind("fe80::1").map(|ret| ret.0), Some(SpecialPurpose::LinkLocal));
+    assert_eq!(kind("ff02::1").map(|ret| ret.0), Some(SpecialPurpose::Multicast));
+    assert_eq!(kind("2001:db8::1").map(|ret| ret.0), Some(SpecialPurpose::Documentation));
+    // Most specific block
+    assert_eq!(kind("2001::1").map(|ret| ret.0), Some(SpecialPurpose::Teredo));
+    assert_eq!(kind("2001:2::1").map(|ret| ret.0), Some(SpecialPurpose::Benchmarking));
+    assert_eq!(kind("2001:100::1").map(|ret| ret.0), Some(SpecialPurpose::IetfProtocol));
+    assert_eq!(kind("2001:200::1"), None);
+    assert_eq!(kind("2400:cb00::1"), None);
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::str::FromStr;
+use std::convert::TryFrom;
+
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct InvalidStatus;
+
+impl std::error::Error for InvalidStatus { }
+
+impl fmt::Display for InvalidStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InvalidStatus")
+    }
+}
+
+
+/// Allocation status of a delegated block
+/// 
+/// `https://www.apnic.net/about-apnic/corporate-documents/documents/resource-guidelines/rir-statistics-exchange-format/`
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    /// Allocated to an LIR, which may assign it further
+    Allocated = 0u8,
+    /// Assigned to an end user
+    Assigned,
+    /// Not yet delegated by the RIR
+    Available,
+    /// Held back by the RIR
+    Reserved,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [
+        Status::Allocated,
+        Status::Assigned,
+        Status::Available,
+        Status::Reserved,
+    ];
+
+    pub fn from_index(index: u8) -> Result<Self, InvalidStatus> {
+        match index {
+            0 => Ok(Status::Allocated),
+            1 => Ok(Status::Assigned),
+            2 => Ok(Status::Available),
+            3 => Ok(Status::Reserved),
+            _ => Err(InvalidStatus)
+        }
+    }
+
+    pub fn index(&self) -> u8 {
+        match *self {
+            Status::Allocated => 0,
+            Status::Assigned => 1,
+            Status::Available => 2,
+            Status::Reserved => 3,
+        }
+    }
+
+    /// `Allocated` or `Assigned`
+    pub fn is_delegated(&self) -> bool {
+        match *self {
+            Status::Allocated | Status::Assigned => true,
+            Status::Available | Status::Reserved => false,
+        }
+    }
+}
+
+impl From<Status> for u8 {
+    fn from(status: Status) -> u8 {
+        status.index()
+    }
+}
+
+impl TryFrom<u8> for Status {
+    type Error = InvalidStatus;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        Status::from_index(index)
+    }
+}
+
+impl FromStr for Status {
+    type Err = InvalidStatus;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allocated" => Ok(Status::Allocated),
+            "assigned"  => Ok(Status::Assigned),
+            "available" => Ok(Status::Available),
+            "reserved"  => Ok(Status::Reserved),
+                      _ => Err(InvalidStatus),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Status::Allocated => write!(f, "allocated"),
+            Status::Assigned => write!(f, "assigned"),
+            Status::Available => write!(f, "available"),
+            Status::Reserved => write!(f, "reserved"),
+        }
+    }
+}
+
+
+/// A set of `Status`, see `Database::lookup_filtered`.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct StatusFilter(u8);
+
+impl StatusFilter {
+    pub const ALL: Self = Self(0b1111);
+    /// `Allocated` and `Assigned`, i.e. space that is actually in use.
+    pub const DELEGATED: Self = Self(0b0011);
+    pub const NONE: Self = Self(0);
+
+    #[inline]
+    pub fn contains(&self, status: Status) -> bool {
+        self.0 & (1 << status.index()) != 0
+    }
+
+    #[inline]
+    pub fn with(self, status: Status) -> Self {
+        Self(self.0 | (1 << status.index()))
+    }
+
+    #[inline]
+    pub fn without(self, status: Status) -> Self {
+        Self(self.0 & !(1 << status.index()))
+    }
+}
+
+impl Default for StatusFilter {
+    fn default() -> Self {
+        StatusFilter::ALL
+    }
+}
+
+
+#[test]
+fn test_status_round_trip() {
+    for status in Status::ALL.iter() {
+        assert_eq!(Status::from_index(status.index()), Ok(*status));
+        assert_eq!(Status::try_from(u8::from(*status)), Ok(*status));
+        assert_eq!(format!("{}", status).parse::<Status>(), Ok(*status));
+    }
+
+    assert_eq!(Status::from_index(Status::ALL.len() as u8), Err(InvalidStatus));
+    assert_eq!("Allocated".parse::<Status>(), Err(InvalidStatus));
+}
+
+#[test]
+fn test_status_filter() {
+    for status in Status::ALL.iter() {
+        assert!(StatusFilter::ALL.contains(*status));
+        assert!(!StatusFilter::NONE.contains(*status));
+        assert_eq!(StatusFilter::DELEGATED.contains(*status), status.is_delegated());
+    }
+
+    let filter = StatusFilter::DELEGATED.with(Status::Reserved);
+    assert!(filter.contains(Status::Reserved));
+    assert!(!filter.contains(Status::Available));
+    assert_eq!(filter.without(Status::Reserved), StatusFilter::DELEGATED);
+}
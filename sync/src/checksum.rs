@@ -0,0 +1,93 @@
+use std::fmt;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumError {
+    /// The `.md5` file has no digest in a known format.
+    Unrecognized(String),
+    Mismatch { expected: String, actual: String },
+}
+
+impl std::error::Error for ChecksumError { }
+
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumError::Unrecognized(content) => write!(f, "unrecognized md5 file: {:?}", content),
+            ChecksumError::Mismatch { expected, actual } => write!(f, "md5 mismatch: expected {}, got {}", expected, actual),
+        }
+    }
+}
+
+
+fn is_digest(s: &str) -> bool {
+    s.len() == 32 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The lowercase hex digest of a `.md5` file.
+///
+/// The RIRs do not agree on a format:
+///
+///      MD5 (delegated-ripencc-latest) = 0123456789abcdef0123456789abcdef
+///      0123456789abcdef0123456789abcdef  delegated-lacnic-latest
+///      0123456789abcdef0123456789abcdef
+pub fn parse_md5(content: &[u8]) -> Result<String, ChecksumError> {
+    let text = String::from_utf8_lossy(content);
+    let line = text.lines().map(|line| line.trim()).find(|line| !line.is_empty()).unwrap_or("");
+
+    let digest = if line.starts_with("MD5") {
+        // BSD style
+        line.rsplit('=').next().map(|s| s.trim())
+    } else {
+        // GNU style, `*` marks binary mode
+        line.split_whitespace().next()
+    };
+
+    match digest {
+        Some(digest) if is_digest(digest) => Ok(digest.to_ascii_lowercase()),
+        _ => Err(ChecksumError::Unrecognized(line.to_string())),
+    }
+}
+
+pub fn md5_hex(content: &[u8]) -> String {
+    format!("{:x}", md5::compute(content))
+}
+
+/// Check `content` against the digest in the `.md5` file `md5_content`.
+pub fn verify(content: &[u8], md5_content: &[u8]) -> Result<(), ChecksumError> {
+    let expected = parse_md5(md5_content)?;
+    let actual = md5_hex(content);
+
+    if expected != actual {
+        return Err(ChecksumError::Mismatch { expected, actual });
+    }
+
+    Ok(())
+}
+
+
+#[test]
+fn test_parse_md5() {
+    let digest = "9e107d9d372bb6826bd81d3542a419d6";
+
+    assert_eq!(parse_md5(b"MD5 (delegated-apnic-latest) = 9e107d9d372bb6826bd81d3542a419d6\n").unwrap(), digest);
+    assert_eq!(parse_md5(b"9e107d9d372bb6826bd81d3542a419d6  delegated-lacnic-latest\n").unwrap(), digest);
+    assert_eq!(parse_md5(b"9E107D9D372BB6826BD81D3542A419D6 *delegated-afrinic-latest").unwrap(), digest);
+    assert_eq!(parse_md5(b"\n9e107d9d372bb6826bd81d3542a419d6\n").unwrap(), digest);
+
+    assert!(parse_md5(b"").is_err());
+    assert!(parse_md5(b"<html>Not Found</html>").is_err());
+    assert!(parse_md5(b"MD5 (delegated-apnic-latest) = 9e107d9d").is_err());
+}
+
+#[test]
+fn test_verify() {
+    let content = b"The quick brown fox jumps over the lazy dog";
+    assert_eq!(md5_hex(content), "9e107d9d372bb6826bd81d3542a419d6");
+
+    assert!(verify(content, b"MD5 (x) = 9e107d9d372bb6826bd81d3542a419d6").is_ok());
+    match verify(&content[..10], b"MD5 (x) = 9e107d9d372bb6826bd81d3542a419d6") {
+        Err(ChecksumError::Mismatch { expected, .. }) => assert_eq!(expected, "9e107d9d372bb6826bd81d3542a419d6"),
+        ret => panic!("{:?}", ret),
+    }
+}
@@ -0,0 +1,73 @@
+// Compressed dated files, e.g. RIPE NCC keeps the files of past days only as
+// `2026/delegated-ripencc-20260101.bz2`.
+use std::io::{self, Read};
+use std::fmt;
+
+
+/// Picked by the extension of the download URL.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+}
+
+impl Compression {
+    pub fn of(url: &str) -> Compression {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+
+        if path.ends_with(".gz") {
+            Compression::Gzip
+        } else if path.ends_with(".bz2") {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+
+    /// The file inside `content`.
+    pub fn decompress(&self, content: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+
+        match *self {
+            Compression::None => output.extend_from_slice(content),
+            Compression::Gzip => {
+                flate2::read::MultiGzDecoder::new(content).read_to_end(&mut output)?;
+            },
+            Compression::Bzip2 => {
+                bzip2::read::MultiBzDecoder::new(content).read_to_end(&mut output)?;
+            },
+        }
+
+        Ok(output)
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+#[test]
+fn test_compression() {
+    use std::io::Write;
+
+    assert_eq!(Compression::of("https://ftp.ripe.net/pub/stats/ripencc/2026/delegated-ripencc-20260101.bz2"), Compression::Bzip2);
+    assert_eq!(Compression::of("https://ftp.apnic.net/stats/apnic/2026/delegated-apnic-20260101.gz"), Compression::Gzip);
+    assert_eq!(Compression::of("https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"), Compression::None);
+
+    let content = b"2|ripencc|20260101|0|19830705|20260101|+0100\n";
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(content).unwrap();
+    assert_eq!(Compression::Gzip.decompress(&gz.finish().unwrap()).unwrap(), &content[..]);
+
+    let mut bz2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+    bz2.write_all(content).unwrap();
+    assert_eq!(Compression::Bzip2.decompress(&bz2.finish().unwrap()).unwrap(), &content[..]);
+
+    assert_eq!(Compression::None.decompress(content).unwrap(), &content[..]);
+    assert!(Compression::Bzip2.decompress(content).is_err());
+}
@@ -0,0 +1,162 @@
+use hyper::Client;
+use hyper::client::HttpConnector;
+use hyper::body::Bytes;
+use hyper::body::to_bytes as body_to_bytes;
+use hyper_tls::HttpsConnector;
+
+use std::io;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::Error;
+use crate::ftp;
+use crate::validators::Validators;
+
+
+type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+
+
+/// Result of a (conditional) request.
+pub enum Fetched {
+    /// `304 Not Modified`, the local copy is current.
+    NotModified,
+    Modified(Bytes, Validators),
+}
+
+
+/// The backends, picked by the scheme of the URL.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Scheme {
+    Https,
+    /// Plain HTTP, for mirrors on a local network.
+    Http,
+    /// Anonymous passive mode FTP unless the URL has a user.
+    Ftp,
+    /// `file:///path`, a local directory, e.g. to test sync offline.
+    File,
+}
+
+impl Scheme {
+    pub fn of(url: &str) -> Option<Scheme> {
+        let scheme = &url[..url.find("://")?];
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "https" => Some(Scheme::Https),
+            "http" => Some(Scheme::Http),
+            "ftp" => Some(Scheme::Ftp),
+            "file" => Some(Scheme::File),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+// `file:///data/x` and `file://localhost/data/x` => `/data/x`
+fn file_path(url: &str) -> io::Result<PathBuf> {
+    let rest = &url["file://".len()..];
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid file url: {}", url)));
+    }
+
+    Ok(PathBuf::from(path))
+}
+
+
+/// Downloads a URL with the backend of its scheme.
+#[derive(Clone)]
+pub struct Fetcher {
+    client: HttpsClient,
+}
+
+impl Fetcher {
+    pub fn new() -> Self {
+        // NOTE: HttpsConnector 同时支持 http 和 https。
+        let https = HttpsConnector::new();
+        let client: HttpsClient = Client::builder().build::<_, hyper::Body>(https);
+
+        Fetcher { client }
+    }
+
+    /// Download `url` once, no timeout and no retry.
+    ///
+    /// `validators` make HTTP(S) requests conditional, other backends always
+    /// download the whole file and return no validators.
+    pub async fn fetch(&self, url: &str, validators: &Validators) -> Result<Fetched, Error> {
+        match Scheme::of(url) {
+            Some(Scheme::Https) | Some(Scheme::Http) => self.fetch_http(url, validators).await,
+            Some(Scheme::Ftp) => {
+                let content = ftp::retr(url).await?;
+                Ok(Fetched::Modified(content, Validators::default()))
+            },
+            Some(Scheme::File) => {
+                let path = file_path(url)?;
+                let content = tokio::task::spawn_blocking(move || std::fs::read(path)).await??;
+                Ok(Fetched::Modified(Bytes::from(content), Validators::default()))
+            },
+            None => {
+                let e = io::Error::new(io::ErrorKind::Other, format!("URL Scheme Not Supported: {}", url));
+                Err(Box::new(e))
+            },
+        }
+    }
+
+    async fn fetch_http(&self, url: &str, validators: &Validators) -> Result<Fetched, Error> {
+        let uri = url.parse::<hyper::Uri>()?;
+        let req = validators.apply(hyper::Request::get(uri)).body(hyper::Body::empty())?;
+        let res = self.client.request(req).await?;
+        if res.status() == 304 && !validators.is_empty() {
+            return Ok(Fetched::NotModified);
+        }
+        if res.status() != 200 {
+            let e = io::Error::new(io::ErrorKind::Other, format!("Http Status Code: {:?}", res.status()));
+            return Err(Box::new(e));
+        }
+
+        let new_validators = Validators::from_headers(url, res.headers());
+        let body = body_to_bytes(res).await?;
+
+        Ok(Fetched::Modified(body, new_validators))
+    }
+}
+
+
+#[test]
+fn test_scheme() {
+    assert_eq!(Scheme::of("https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"), Some(Scheme::Https));
+    assert_eq!(Scheme::of("HTTP://10.0.0.1/delegated-apnic-latest"), Some(Scheme::Http));
+    assert_eq!(Scheme::of("ftp://ftp.apnic.net/public/stats/iana/delegated-iana-extended-latest"), Some(Scheme::Ftp));
+    assert_eq!(Scheme::of("file:///srv/mirror/delegated-apnic-latest"), Some(Scheme::File));
+    assert_eq!(Scheme::of("rsync://ftp.apnic.net/stats"), None);
+    assert_eq!(Scheme::of("delegated-apnic-latest"), None);
+
+    assert_eq!(file_path("file:///srv/mirror/x").unwrap(), PathBuf::from("/srv/mirror/x"));
+    assert_eq!(file_path("file://localhost/srv/mirror/x").unwrap(), PathBuf::from("/srv/mirror/x"));
+    assert!(file_path("file://mirror/x").is_err());
+}
+
+#[test]
+fn test_fetch_file() {
+    let path = std::env::temp_dir().join(format!("sync-fetch-{}", std::process::id()));
+    std::fs::write(&path, b"2|iana|20261016|0|19830101|20261016|+0000\n").unwrap();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let url = format!("file://{}", path.display());
+    match rt.block_on(Fetcher::new().fetch(&url, &Validators::default())).unwrap() {
+        Fetched::Modified(content, validators) => {
+            assert_eq!(&content[..], &b"2|iana|20261016|0|19830101|20261016|+0000\n"[..]);
+            assert!(validators.is_empty());
+        },
+        Fetched::NotModified => panic!("not modified"),
+    }
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(rt.block_on(Fetcher::new().fetch(&url, &Validators::default())).is_err());
+    assert!(rt.block_on(Fetcher::new().fetch("rsync://x/y", &Validators::default())).is_err());
+}
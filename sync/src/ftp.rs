@@ -0,0 +1,291 @@
+// Minimal passive mode FTP client, enough to `RETR` one file.
+//
+// RFC 959, https://www.rfc-editor.org/rfc/rfc959
+use hyper::body::Bytes;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf;
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+
+fn protocol_error(text: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+
+/// `ftp://[user[:password]@]host[:port]/path`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtpUrl {
+    pub user: String,
+    pub password: String,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl FtpUrl {
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid ftp url: {}", url));
+
+        let rest = url.strip_prefix("ftp://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => return Err(invalid()),
+        };
+
+        let (user, password, hostport) = match authority.rfind('@') {
+            Some(idx) => {
+                let mut userinfo = authority[..idx].splitn(2, ':');
+                let user = userinfo.next().unwrap_or("").to_string();
+                let password = userinfo.next().unwrap_or("").to_string();
+                (user, password, &authority[idx + 1..])
+            },
+            None => ("anonymous".to_string(), "anonymous@".to_string(), authority),
+        };
+
+        let (host, port) = match hostport.rfind(':') {
+            Some(idx) if !hostport.ends_with(']') => {
+                (&hostport[..idx], hostport[idx + 1..].parse::<u16>().map_err(|_| invalid())?)
+            },
+            _ => (hostport, 21),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() || path.len() < 2 {
+            return Err(invalid());
+        }
+
+        Ok(FtpUrl { user, password, host: host.to_string(), port, path: path.to_string() })
+    }
+}
+
+
+async fn read_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, line: &mut String) -> io::Result<()> {
+    line.clear();
+    if reader.read_line(line).await? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "ftp connection closed"));
+    }
+
+    Ok(())
+}
+
+// A reply, multi-line replies are joined: `213-...` lines until `213 ...`.
+async fn read_reply<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> io::Result<(u32, String)> {
+    let mut line = String::new();
+    read_line(reader, &mut line).await?;
+
+    if line.len() < 4 || !line.as_bytes()[..3].iter().all(|b| b.is_ascii_digit()) || !matches!(&line[3..4], " " | "-") {
+        return Err(protocol_error(format!("malformed ftp reply: {:?}", line)));
+    }
+    let code = line[..3].parse::<u32>().map_err(|_| protocol_error(line.clone()))?;
+    let end = format!("{} ", &line[..3]);
+    let mut text = line.clone();
+
+    // NOTE: 多行回复只在与首行相同的 "ddd " 处结束，中间的行也可能以其他数字开头。
+    while !line.starts_with(&end) {
+        read_line(reader, &mut line).await?;
+        text.push_str(&line);
+    }
+
+    Ok((code, text.trim_end().to_string()))
+}
+
+async fn command<R: AsyncRead + Unpin>(reader: &mut BufReader<R>, writer: &mut OwnedWriteHalf, cmd: &str, expected: &[u32]) -> io::Result<(u32, String)> {
+    writer.write_all(format!("{}\r\n", cmd).as_bytes()).await?;
+
+    let (code, text) = read_reply(reader).await?;
+    if !expected.contains(&code) {
+        // NOTE: 不要把密码写进错误信息。
+        let cmd = if cmd.starts_with("PASS ") { "PASS ****" } else { cmd };
+        return Err(protocol_error(format!("{}: {}", cmd, text)));
+    }
+
+    Ok((code, text))
+}
+
+// `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2).`
+fn parse_pasv_port(text: &str) -> Option<u16> {
+    let start = text.find('(')?;
+    let end = text[start..].find(')')? + start;
+    let numbers = text[start + 1..end].split(',')
+        .map(|n| n.trim().parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>().ok()?;
+    if numbers.len() != 6 {
+        return None;
+    }
+
+    Some((numbers[4] as u16) << 8 | numbers[5] as u16)
+}
+
+// `229 Entering Extended Passive Mode (|||port|)`, RFC 2428.
+fn parse_epsv_port(text: &str) -> Option<u16> {
+    let start = text.find('(')?;
+    let end = text[start..].find(')')? + start;
+    let inner = &text[start + 1..end];
+    let delimiter = inner.chars().next()?;
+    let fields = inner.split(delimiter).collect::<Vec<&str>>();
+    if fields.len() != 5 {
+        return None;
+    }
+
+    fields[3].parse::<u16>().ok()
+}
+
+/// Download the file at `url`.
+pub async fn retr(url: &str) -> io::Result<Bytes> {
+    let url = FtpUrl::parse(url)?;
+
+    let stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+    let peer_ip: IpAddr = stream.peer_addr()?.ip();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let (code, text) = read_reply(&mut reader).await?;
+    if code != 220 {
+        return Err(protocol_error(text));
+    }
+
+    let (code, _) = command(&mut reader, &mut writer, &format!("USER {}", url.user), &[230, 331]).await?;
+    if code == 331 {
+        command(&mut reader, &mut writer, &format!("PASS {}", url.password), &[230, 202]).await?;
+    }
+    command(&mut reader, &mut writer, "TYPE I", &[200]).await?;
+
+    // NOTE: 先试 EPSV，IPv6 只能用它；服务器不支持时退回 PASV。
+    //       忽略 PASV 返回的地址，NAT 后面的服务器常常返回内网地址。
+    let port = match command(&mut reader, &mut writer, "EPSV", &[229]).await {
+        Ok((_, text)) => parse_epsv_port(&text).ok_or_else(|| protocol_error(text.clone()))?,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData && peer_ip.is_ipv4() => {
+            let (_, text) = command(&mut reader, &mut writer, "PASV", &[227]).await?;
+            parse_pasv_port(&text).ok_or_else(|| protocol_error(text.clone()))?
+        },
+        Err(e) => return Err(e),
+    };
+    let mut data = TcpStream::connect(SocketAddr::new(peer_ip, port)).await?;
+
+    command(&mut reader, &mut writer, &format!("RETR {}", url.path), &[125, 150]).await?;
+
+    let mut content = Vec::new();
+    data.read_to_end(&mut content).await?;
+    drop(data);
+
+    let (code, text) = read_reply(&mut reader).await?;
+    if code != 226 && code != 250 {
+        return Err(protocol_error(text));
+    }
+    let _ = writer.write_all(b"QUIT\r\n").await;
+
+    Ok(Bytes::from(content))
+}
+
+
+#[test]
+fn test_ftp_url() {
+    let url = FtpUrl::parse("ftp://ftp.apnic.net/public/stats/iana/delegated-iana-extended-latest").unwrap();
+    assert_eq!(url.user, "anonymous");
+    assert_eq!((url.host.as_str(), url.port), ("ftp.apnic.net", 21));
+    assert_eq!(url.path, "/public/stats/iana/delegated-iana-extended-latest");
+
+    let url = FtpUrl::parse("ftp://sync:secret@[::1]:2121/stats/x").unwrap();
+    assert_eq!((url.user.as_str(), url.password.as_str()), ("sync", "secret"));
+    assert_eq!((url.host.as_str(), url.port), ("::1", 2121));
+
+    assert!(FtpUrl::parse("ftp://ftp.apnic.net").is_err());
+    assert!(FtpUrl::parse("ftp://ftp.apnic.net:ftp/x").is_err());
+    assert!(FtpUrl::parse("https://ftp.apnic.net/x").is_err());
+
+    assert_eq!(parse_pasv_port("227 Entering Passive Mode (10,0,0,1,195,80)."), Some(50000));
+    assert_eq!(parse_pasv_port("227 Entering Passive Mode"), None);
+
+    assert_eq!(parse_epsv_port("229 Entering Extended Passive Mode (|||50000|)"), Some(50000));
+    assert_eq!(parse_epsv_port("229 Entering Extended Passive Mode (!!!21!)"), Some(21));
+    assert_eq!(parse_epsv_port("229 Entering Extended Passive Mode (|||x|)"), None);
+}
+
+#[test]
+fn test_ftp_reply() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let read = |input: &'static [u8]| rt.block_on(read_reply(&mut BufReader::new(input)));
+
+    assert_eq!(read(b"220 ready\r\n").unwrap(), (220, "220 ready".to_string()));
+
+    let (code, text) = read(b"211-Features:\r\n 211 MDTM\r\n200 not the end\r\n211 End\r\n").unwrap();
+    assert_eq!(code, 211);
+    assert!(text.ends_with("211 End"));
+    assert!(text.contains("200 not the end"));
+
+    assert!(read(b"211-Features:\r\n200 not the end\r\n").is_err());
+    assert!(read(b"hello\r\n").is_err());
+}
+
+#[cfg(test)]
+fn ftp_server(epsv: bool) -> (u16, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, Write};
+    use std::net::TcpListener;
+
+    let control = TcpListener::bind("127.0.0.1:0").unwrap();
+    let data = TcpListener::bind("127.0.0.1:0").unwrap();
+    let (control_port, data_port) = (control.local_addr().unwrap().port(), data.local_addr().unwrap().port());
+
+    let server = std::thread::spawn(move || {
+        let (stream, _) = control.accept().unwrap();
+        let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+
+        writer.write_all(b"220-Welcome\r\n220 ready\r\n").unwrap();
+        let mut commands = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let line = line.trim_end().to_string();
+            if line == "QUIT" {
+                break;
+            }
+            let reply = match line.split(' ').next().unwrap() {
+                "USER" => "331 password please\r\n".to_string(),
+                "PASS" => "230 logged in\r\n".to_string(),
+                "TYPE" => "200 binary\r\n".to_string(),
+                "EPSV" if epsv => format!("229 Entering Extended Passive Mode (|||{}|)\r\n", data_port),
+                "PASV" => format!("227 Entering Passive Mode (10,0,0,1,{},{}).\r\n", data_port >> 8, data_port & 0xff),
+                "RETR" => {
+                    writer.write_all(b"150 sending\r\n").unwrap();
+                    let (mut data_stream, _) = data.accept().unwrap();
+                    data_stream.write_all(b"2|iana|20261016|0|19830101|20261016|+0000\n").unwrap();
+                    drop(data_stream);
+                    "226 done\r\n".to_string()
+                },
+                _ => "502 not implemented\r\n".to_string(),
+            };
+            writer.write_all(reply.as_bytes()).unwrap();
+            commands.push(line);
+        }
+
+        commands
+    });
+
+    (control_port, server)
+}
+
+#[test]
+fn test_ftp_retr() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    for &epsv in &[true, false] {
+        let (port, server) = ftp_server(epsv);
+        let url = format!("ftp://127.0.0.1:{}/stats/delegated-iana-extended-latest", port);
+        let content = rt.block_on(retr(&url)).unwrap();
+        assert_eq!(&content[..], &b"2|iana|20261016|0|19830101|20261016|+0000\n"[..]);
+
+        let commands = server.join().unwrap();
+        let passive: &[&str] = if epsv { &["EPSV"] } else { &["EPSV", "PASV"] };
+        let expected = ["USER anonymous", "PASS anonymous@", "TYPE I"].iter()
+            .chain(passive)
+            .chain(&["RETR /stats/delegated-iana-extended-latest"])
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(commands, expected);
+    }
+}
@@ -2,98 +2,198 @@ extern crate getopts;
 extern crate tokio;
 extern crate hyper;
 extern crate hyper_tls;
+extern crate md5;
+extern crate flate2;
+extern crate bzip2;
+extern crate sources;
+
+mod checksum;
+mod compression;
+#[allow(dead_code)]
+#[path = "../../src/date.rs"]
+mod date;
+mod fetcher;
+mod ftp;
+mod snapshot;
+mod staging;
+mod validators;
 
-use hyper::Uri;
-use hyper::Client;
 use hyper::body::Bytes;
-use hyper::body::to_bytes as body_to_bytes;
-use hyper_tls::HttpsConnector;
+use tokio::sync::Semaphore;
+
+use self::compression::Compression;
+use self::date::Date;
+use self::fetcher::{Fetched, Fetcher};
+use sources::{snapshot_path, Source, Sources};
+use self::staging::Staging;
+use self::validators::Validators;
 
-use std::io::{self, Write};
+use std::cmp;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
 use std::path::{Path, PathBuf};
-use std::fs::{self, File, OpenOptions};
-
-
-// Files not exists
-// ("delegated-arin-latest",             "https://ftp.arin.net/pub/stats/arin/delegated-arin-latest"),
-// ("delegated-iana-extended-latest",    "ftp://ftp.apnic.net/public/stats/iana/delegated-iana-extended-latest"),
-pub const IANA_RIR_FILES: [(&str, &str); 10] = [
-    ("delegated-arin-extended-latest",    "https://ftp.arin.net/pub/stats/arin/delegated-arin-extended-latest"),
-    ("delegated-ripencc-latest",          "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-latest"),
-    ("delegated-ripencc-extended-latest", "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-extended-latest"),
-    ("delegated-apnic-latest",            "https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"),
-    ("delegated-apnic-extended-latest",   "https://ftp.apnic.net/stats/apnic/delegated-apnic-extended-latest"),
-    ("delegated-lacnic-latest",           "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-latest"),
-    ("delegated-lacnic-extended-latest",  "https://ftp.lacnic.net/pub/stats/lacnic/delegated-lacnic-extended-latest"),
-    ("delegated-afrinic-latest",          "https://ftp.afrinic.net/pub/stats/afrinic/delegated-afrinic-latest"),
-    ("delegated-afrinic-extended-latest", "https://ftp.afrinic.net/pub/stats/afrinic/delegated-afrinic-extended-latest"),
-    ("delegated-iana-latest",             "https://ftp.apnic.net/stats/iana/delegated-iana-latest"),
-];
-
-async fn fetch(uri: Uri) -> Result<Bytes, Box<dyn std::error::Error>> {
-    let https = HttpsConnector::new();
-    let client = Client::builder().build::<_, hyper::Body>(https);
-
-    let res = client.get(uri).await?;
-    if res.status() != 200 {
-        let e = io::Error::new(io::ErrorKind::Other, format!("Http Status Code: {:?}", res.status()));
-        return Err(Box::new(e));
-    }
+use std::fs;
 
-    let body = body_to_bytes(res).await?;
 
-    Ok(body)
-}
+type Error = Box<dyn std::error::Error + Send + Sync>;
 
-async fn sync(data_path: PathBuf, filename: &str, fileurl: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let fileuri = fileurl.parse::<Uri>()?;
-    assert_eq!(fileuri.scheme_str(), Some("https"), "URL Scheme Not Supported.");
 
-    let filepath = data_path.join(filename);
-    let md5_filepath = data_path.join(format!("{}.md5", filename));
-    let md5_fileuri  = (format!("{}.md5", fileuri)).parse::<Uri>()?;
+/// Fetch `url`, conditionally if `validators` is not empty. Each attempt is
+/// limited to `config.timeout`, failed attempts are retried `config.retries`
+/// times with exponential backoff.
+async fn fetch(fetcher: &Fetcher, url: &str, validators: &Validators, config: &Config) -> Result<Fetched, Error> {
+    let mut attempt = 0u32;
 
-    if filename != "delegated-iana-latest" {
-        let mut old_md5_file_content = Vec::new();
+    loop {
+        let ret = match tokio::time::timeout(config.timeout, fetcher.fetch(url, validators)).await {
+            Ok(ret) => ret,
+            Err(_) => Err(Box::new(io::Error::new(io::ErrorKind::TimedOut, format!("timed out after {:?}", config.timeout))) as Error),
+        };
 
-        if !md5_filepath.exists() {
-            File::create(&md5_filepath)?;
-        } else {
-            old_md5_file_content = std::fs::read(&md5_filepath)?;
+        match ret {
+            Ok(fetched) => return Ok(fetched),
+            Err(e) if attempt < config.retries => {
+                // 1s, 2s, 4s, ...
+                let backoff = config.backoff * 2u32.saturating_pow(attempt);
+                println!("retry {} in {:?} ({}/{}): {}", url, backoff, attempt + 1, config.retries, e);
+
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            },
+            Err(e) => return Err(e),
         }
+    }
+}
 
-        let md5_file_content: Bytes = fetch(md5_fileuri).await?;
+/// Unconditional `fetch`.
+async fn fetch_body(fetcher: &Fetcher, url: &str, config: &Config) -> Result<Bytes, Error> {
+    match fetch(fetcher, url, &Validators::default(), config).await? {
+        Fetched::Modified(body, _) => Ok(body),
+        // NOTE: 没有发送条件请求，不应该收到 304。
+        Fetched::NotModified => Err(Box::new(io::Error::new(io::ErrorKind::InvalidData, format!("{}: unexpected 304 Not Modified", url)))),
+    }
+}
 
-        if md5_file_content.is_empty() || md5_file_content != old_md5_file_content {
-            // NOTE: 数据需要更新
-            let content: Bytes = fetch(fileuri).await?;
-            let mut file = OpenOptions::new().create(true).write(true).append(false).open(&filepath)?;
-            file.write_all(&content)?;
+/// Download `source` from `fileurl` into `staging` unless the copy in the
+/// current generation is up to date.
+///
+/// The download is a conditional request when the local copy has validators
+/// from `fileurl`, see `Validators`. A checksum, if any, is checked whether the file was
+/// downloaded or not modified. `.gz` and `.bz2` files are verified as
+/// downloaded and stored decompressed.
+async fn sync_from(fetcher: &Fetcher, config: &Config, staging: &Staging, source: &Source, fileurl: &str) -> Result<(), Error> {
+    let filename = source.name.as_str();
 
-            // 更新 MD5 校验文件
-            let mut file = OpenOptions::new().create(false).write(true).append(false).open(&md5_filepath)?;
-            file.write_all(&md5_file_content)?;
-        } else {
-            // NOTE: 数据不需要更新
-            return Ok(());
-        }
-    } else {
+    let previous = staging.previous();
+    let old_content = previous.and_then(|dir| fs::read(dir.join(filename)).ok());
+    // NOTE: 压缩文件的 MD5 是压缩包的，本地保存的是解压后的内容，无法再校验。
+    let compression = Compression::of(fileurl);
+    let verifiable = compression == Compression::None;
+
+    let md5_file_content = match source.checksum_url(fileurl) {
+        Some(md5_fileurl) => {
+            let md5_file_content: Bytes = fetch_body(fetcher, &md5_fileurl, config).await?;
+
+            // NOTE: MD5 没有变化并且本地文件校验通过时，数据不需要更新。
+            let old_md5_file_content = previous.and_then(|dir| fs::read(dir.join(format!("{}.md5", filename))).ok()).unwrap_or_default();
+            if md5_file_content == old_md5_file_content {
+                if let Some(ref old_content) = old_content {
+                    if !verifiable || checksum::verify(old_content, &md5_file_content).is_ok() {
+                        return Ok(());
+                    }
+                }
+            }
+
+            Some(md5_file_content)
+        },
         // NOTE: delegated-iana-latest 文件没有 MD5 校验码。
-        let content: Bytes = fetch(fileuri).await?;
-        let mut file = OpenOptions::new().create(true).write(true).append(false).open(&filepath)?;
-        file.write_all(&content)?;
+        None => None,
+    };
+
+    // NOTE: 本地文件不存在或者校验失败时不发送条件请求。
+    let validators = match (&old_content, &md5_file_content) {
+        (None, _) => Validators::default(),
+        (Some(old_content), Some(md5_file_content)) if verifiable && checksum::verify(old_content, md5_file_content).is_err() => {
+            Validators::default()
+        },
+        (Some(_), _) => match previous {
+            Some(dir) => Validators::load(dir, filename).for_url(fileurl),
+            None => Validators::default(),
+        },
+    };
+
+    match fetch(fetcher, fileurl, &validators, config).await? {
+        Fetched::NotModified => { },
+        Fetched::Modified(content, new_validators) => {
+            // NOTE: 校验失败时保留旧文件，也不更新 MD5 校验文件，下次同步会重新下载。
+            if let Some(ref md5_file_content) = md5_file_content {
+                checksum::verify(&content, md5_file_content)?;
+            }
+            let content = compression.decompress(&content)?;
+
+            staging.write(filename, &content)?;
+            staging.write(&Validators::sidecar_name(filename), new_validators.to_string().as_bytes())?;
+        },
+    }
+
+    if let Some(ref md5_file_content) = md5_file_content {
+        // 更新 MD5 校验文件
+        staging.write(&format!("{}.md5", filename), md5_file_content)?;
     }
 
     Ok(())
 }
 
+/// Sync `source` from its URL, failing over to the mirrors in order.
+async fn sync(fetcher: &Fetcher, config: &Config, staging: &Staging, source: &Source) -> Result<(), Error> {
+    let mut urls = source.urls().peekable();
 
-fn boot() -> PathBuf {
+    loop {
+        let fileurl = urls.next().expect("a source has at least one URL");
+        match sync_from(fetcher, config, staging, source, fileurl).await {
+            Ok(()) => return Ok(()),
+            Err(e) => match urls.peek() {
+                Some(mirror) => println!("sync {} from {} failed: {}, trying {}", source.name, fileurl, e, mirror),
+                None => return Err(e),
+            },
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct Config {
+    pub data_path: PathBuf,
+    pub sources: Sources,
+    /// Number of files downloaded at the same time.
+    pub jobs: usize,
+    /// Limit of each request, including the body.
+    pub timeout: Duration,
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each following one.
+    pub backoff: Duration,
+    /// Days to sync dated snapshots of, the `-latest` files if empty.
+    pub dates: Vec<Date>,
+    /// Remove snapshots older than this many days.
+    pub keep_days: Option<u64>,
+    /// Move the files that synced into the data path even if others failed.
+    pub partial: bool,
+}
+
+fn boot() -> Config {
     let args: Vec<String> = std::env::args().collect();
     let program = args[0].clone();
 
     let mut opts = getopts::Options::new();
     opts.optopt("o", "data-path", "Specify the default data path", "");
+    opts.optopt("s", "sources", "Load the source list from a TOML file instead of the builtin one", "FILE");
+    opts.optopt("j", "jobs", "Number of files downloaded concurrently (default 4)", "N");
+    opts.optopt("t", "timeout", "Timeout of each request in seconds (default 60)", "SECONDS");
+    opts.optopt("r", "retries", "Retries of a failed request, with exponential backoff (default 3)", "N");
+    opts.optopt("", "date", "Sync the dated files of one day into <data-path>/snapshots/YYYYMMDD", "YYYYMMDD");
+    opts.optopt("", "since", "Sync the dated files of every day from this one to yesterday", "YYYYMMDD");
+    opts.optopt("", "keep-days", "Remove snapshots older than this many days", "DAYS");
     opts.optflag("h", "help", "print this help menu");
 
     let matches = match opts.parse(&args[1..]) {
@@ -109,45 +209,282 @@ fn boot() -> PathBuf {
     }
 
     let value = matches.opt_str("o").unwrap_or("data".to_string());
+    let data_path = Path::new(value.to_lowercase().as_str()).to_path_buf();
+
+    let sources = match Sources::load_or_builtin(matches.opt_str("s")) {
+        Ok(sources) => sources,
+        Err(e) => {
+            println!("[ERROR] --sources: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    let number = |name: &str, default: u64| -> u64 {
+        match matches.opt_str(name).map(|s| s.parse::<u64>()) {
+            Some(Ok(n)) => n,
+            Some(Err(e)) => {
+                println!("[ERROR] --{}: {}", name, e);
+                std::process::exit(1);
+            },
+            None => default,
+        }
+    };
+
+    let jobs = cmp::max(number("j", 4), 1) as usize;
+    let timeout = Duration::from_secs(number("t", 60));
+    let retries = number("r", 3) as u32;
+    let keep_days = matches.opt_str("keep-days").map(|_| number("keep-days", 0));
+
+    let date = |name: &str| -> Option<Date> {
+        match matches.opt_str(name).map(|s| s.parse::<Date>()) {
+            Some(Ok(date)) => Some(date),
+            Some(Err(e)) => {
+                println!("[ERROR] --{}: {}", name, e);
+                std::process::exit(1);
+            },
+            None => None,
+        }
+    };
+
+    // NOTE: 当天的文件通常还没有发布，--since 只同步到昨天。
+    let dates = match (date("date"), date("since")) {
+        (Some(_), Some(_)) => {
+            println!("[ERROR] --date and --since are exclusive");
+            std::process::exit(1);
+        },
+        (Some(date), None) => vec![date],
+        (None, Some(since)) => snapshot::days(since, Date::from_days(snapshot::today().days() - 1).unwrap()),
+        (None, None) => Vec::new(),
+    };
 
-    Path::new(value.to_lowercase().as_str()).to_path_buf()
+    Config { data_path, sources, jobs, timeout, retries, backoff: Duration::from_secs(1), dates, keep_days, partial: false }
 }
 
-async fn run(data_path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    if !data_path.exists() {
-        fs::create_dir(&data_path)?;
+/// Sync every file, returns the number of files and the names of the files
+/// that failed.
+///
+/// Downloads are staged in a new generation that replaces the current one
+/// only when every file succeeded, or at least one with `config.partial`,
+/// `parse` never sees a mix of old and new files, see `Staging`.
+async fn run(config: Config) -> Result<(usize, Vec<String>), Error> {
+    if !config.data_path.exists() {
+        fs::create_dir(&config.data_path)?;
     }
     
-    println!("Data Path: {:?}", &data_path);
+    println!("Data Path: {:?}", &config.data_path);
     println!();
-    
-    for rir_file in IANA_RIR_FILES.iter() {
-        let filename  = rir_file.0;
-        let fileurl   = rir_file.1;
-        let data_path = data_path.clone();
 
-        print!("sync {:34} ...", filename);
+    let fetcher = Fetcher::new();
+    let staging = Arc::new(Staging::new(&config.data_path)?);
+    let config = Arc::new(config);
+    let semaphore = Arc::new(Semaphore::new(config.jobs));
 
-        let ret = sync(data_path, filename, fileurl).await;
-        match ret {
-            Ok(_) => {
-                println!("    [\x1b[32mOK\x1b[0m]");
-            },
-            Err(e) => {
-                println!("    [\x1b[31mFAILED\x1b[0m]  {:?}", e);
+    let mut handles = Vec::new();
+    for source in config.sources.enabled() {
+        let source = source.clone();
+        let fetcher = fetcher.clone();
+        let config = config.clone();
+        let semaphore = semaphore.clone();
+        let staging = staging.clone();
+
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire().await?;
+
+            let ret = sync(&fetcher, &config, &staging, &source).await;
+            match ret {
+                Ok(_) => {
+                    println!("sync {:34} ...    [\x1b[32mOK\x1b[0m]", source.name);
+                },
+                Err(ref e) => {
+                    println!("sync {:34} ...    [\x1b[31mFAILED\x1b[0m]  {:?}", source.name, e);
+                }
             }
+
+            ret
+        }));
+    }
+
+    let mut failed = Vec::new();
+    for (handle, source) in handles.into_iter().zip(config.sources.enabled()) {
+        match handle.await {
+            Ok(Ok(())) => { },
+            Ok(Err(_)) | Err(_) => failed.push(source.name.clone()),
         }
     }
 
-    Ok(())
+    // NOTE: 所有任务都已结束，不会再有其它引用。
+    let staging = Arc::try_unwrap(staging).expect("staging is still in use");
+    let total = config.sources.enabled().count();
+    if failed.is_empty() || (config.partial && failed.len() < total) {
+        for filename in staging.promote()? {
+            println!("update {}", filename);
+        }
+    } else {
+        staging.discard()?;
+    }
+
+    Ok((total, failed))
+}
+
+/// Sync the dated snapshot of every day in `config.dates`, see `run`.
+///
+/// A source that failed is left out of the snapshot of that day and synced
+/// again next time, complete snapshots are skipped.
+async fn run_snapshots(config: &Config) -> Result<(usize, Vec<String>), Error> {
+    let (mut total, mut failed) = (0, Vec::new());
+
+    for date in config.dates.iter() {
+        let date_str = date.to_string();
+        if snapshot::is_complete(&config.data_path, &config.sources, *date) {
+            println!("snapshot {} is complete", date_str);
+            continue;
+        }
+
+        let sources = config.sources.enabled().filter_map(|source| source.dated(&date_str)).collect::<Vec<Source>>();
+        if sources.is_empty() {
+            continue;
+        }
+
+        let data_path = snapshot_path(&config.data_path, &date_str);
+        fs::create_dir_all(&data_path)?;
+
+        let snapshot_config = Config {
+            data_path: data_path.clone(),
+            sources: Sources { sources },
+            dates: Vec::new(),
+            keep_days: None,
+            // NOTE: 某个 RIR 某天没有发布文件时，不影响其它来源的快照。
+            partial: true,
+            ..config.clone()
+        };
+        let (n, snapshot_failed) = run(snapshot_config).await?;
+        total += n;
+        failed.extend(snapshot_failed.iter().map(|filename| format!("{} {}", date_str, filename)));
+
+        // NOTE: 全部失败的日期不保留空目录，下次同步重新下载。
+        if !snapshot_failed.is_empty() && fs::read_dir(&data_path)?.next().is_none() {
+            fs::remove_dir(&data_path)?;
+        }
+    }
+
+    Ok((total, failed))
 }
 
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let data_path = boot();
+    let config = boot();
     
     let rt  = tokio::runtime::Runtime::new()?;
-    rt.block_on(run(data_path))?;
+    let (total, failed) = if config.dates.is_empty() {
+        rt.block_on(run(config.clone()))
+    } else {
+        rt.block_on(run_snapshots(&config))
+    }.map_err(|e| e as Box<dyn std::error::Error>)?;
+
+    if let Some(keep_days) = config.keep_days {
+        let oldest = Date::from_days(snapshot::today().days() - keep_days as i64)?;
+        for date in snapshot::prune(&config.data_path, oldest)? {
+            println!("remove snapshot {}", date);
+        }
+    }
+
+    println!();
+    println!("sync: {} ok, {} failed", total - failed.len(), failed.len());
+    if !failed.is_empty() {
+        for filename in failed.iter() {
+            println!("[ERROR] {}", filename);
+        }
+        std::process::exit(1);
+    }
 
     Ok(())
 }
+
+
+#[test]
+fn test_run_snapshots() {
+    let root = std::env::temp_dir().join(format!("sync-run-snapshots-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let mirror = root.join("mirror");
+    fs::create_dir_all(&mirror).unwrap();
+
+    let publish = |filename: &str| {
+        let content = format!("2|{}|20260101|0|19830101|20260101|+0000\n", filename);
+        fs::write(mirror.join(filename), &content).unwrap();
+        fs::write(mirror.join(format!("{}.md5", filename)), format!("{}  {}\n", checksum::md5_hex(content.as_bytes()), filename)).unwrap();
+    };
+    publish("delegated-apnic-20260101");
+    publish("delegated-apnic-20260102");
+    publish("delegated-lacnic-20260101");
+
+    // RIPE NCC: 2026/delegated-ripencc-20260101.bz2, the MD5 is of the archive
+    fs::create_dir_all(mirror.join("2026")).unwrap();
+    for date in ["20260101", "20260102"].iter() {
+        use std::io::Write;
+
+        let filename = format!("2026/delegated-ripencc-{}.bz2", date);
+        let mut bz2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz2.write_all(format!("2|ripencc|{}|0|19830705|{}|+0100\n", date, date).as_bytes()).unwrap();
+        let content = bz2.finish().unwrap();
+        fs::write(mirror.join(&filename), &content).unwrap();
+        fs::write(mirror.join(format!("{}.md5", filename)), checksum::md5_hex(&content)).unwrap();
+    }
+
+    let sources = Sources::from_toml(&format!(r#"
+[[source]]
+name = "delegated-apnic-latest"
+url = "file://{mirror}/delegated-apnic-latest"
+
+[[source]]
+name = "delegated-lacnic-latest"
+url = "file://{mirror}/delegated-lacnic-latest"
+
+[[source]]
+name = "delegated-ripencc-latest"
+url = "file://{mirror}/delegated-ripencc-latest"
+dated_url = "file://{mirror}/{{year}}/delegated-ripencc-{{date}}.bz2"
+
+[[source]]
+name = "delegated-iana-latest"
+url = "file://{mirror}/delegated-iana-latest"
+dated_files = false
+"#, mirror = mirror.display())).unwrap();
+
+    let dates = vec![Date::new(2026, 1, 1).unwrap(), Date::new(2026, 1, 2).unwrap()];
+    let config = Config {
+        data_path: root.join("data"),
+        sources,
+        jobs: 2,
+        timeout: Duration::from_secs(10),
+        retries: 0,
+        backoff: Duration::from_secs(0),
+        dates: dates.clone(),
+        keep_days: None,
+        partial: false,
+    };
+    let snapshot = |date: &str| sources::current_path(&snapshot_path(&config.data_path, date));
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let (total, failed) = rt.block_on(run_snapshots(&config)).unwrap();
+    assert_eq!(total, 6);
+    // NOTE: LACNIC 没有发布 20260102 的文件，只缺这一个来源，当天其它文件照常保留。
+    assert_eq!(failed, vec!["20260102 delegated-lacnic-latest"]);
+    assert!(snapshot("20260101").join("delegated-lacnic-latest").is_file());
+    assert!(snapshot("20260102").join("delegated-apnic-latest").is_file());
+    assert!(!snapshot("20260102").join("delegated-lacnic-latest").exists());
+    assert_eq!(fs::read_to_string(snapshot("20260102").join("delegated-ripencc-latest")).unwrap(),
+               "2|ripencc|20260102|0|19830705|20260102|+0100\n");
+    assert!(!snapshot("20260101").join("delegated-iana-latest").exists());
+    assert!(!snapshot_path(&config.data_path, "20260102").join(".lock").exists());
+    assert!(snapshot::is_complete(&config.data_path, &config.sources, dates[0]));
+    assert!(!snapshot::is_complete(&config.data_path, &config.sources, dates[1]));
+
+    publish("delegated-lacnic-20260102");
+    let (total, failed) = rt.block_on(run_snapshots(&config)).unwrap();
+    assert_eq!((total, failed.len()), (3, 0));
+    assert_eq!(fs::read(snapshot("20260102").join("delegated-lacnic-latest")).unwrap(),
+               fs::read(mirror.join("delegated-lacnic-20260102")).unwrap());
+    assert!(snapshot::is_complete(&config.data_path, &config.sources, dates[1]));
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,94 @@
+use std::io;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::date::Date;
+use sources::{current_path, snapshot_path, Sources};
+
+
+/// Today in UTC.
+pub fn today() -> Date {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+    Date::from_days((secs / 86400) as i64).expect("system clock out of range")
+}
+
+/// Every day from `first` to `last`, both included.
+pub fn days(first: Date, last: Date) -> Vec<Date> {
+    (first.days()..=last.days()).filter_map(|days| Date::from_days(days).ok()).collect()
+}
+
+/// Dates of the snapshots in `data_path`, sorted.
+pub fn list(data_path: &Path) -> io::Result<Vec<Date>> {
+    let dir = data_path.join("snapshots");
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut dates = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse::<Date>().ok()))
+        .collect::<Vec<Date>>();
+    dates.sort();
+
+    Ok(dates)
+}
+
+/// Every enabled source with a dated URL is in the snapshot of `date`.
+///
+/// Dated files do not change once published, a complete snapshot is not
+/// synced again.
+pub fn is_complete(data_path: &Path, sources: &Sources, date: Date) -> bool {
+    let dir = current_path(&snapshot_path(data_path, &date.to_string()));
+
+    sources.enabled()
+        .filter(|source| source.dated(&date.to_string()).is_some())
+        .all(|source| dir.join(&source.name).is_file())
+}
+
+/// Remove the snapshots older than `oldest`, returns their dates.
+pub fn prune(data_path: &Path, oldest: Date) -> io::Result<Vec<Date>> {
+    let mut removed = Vec::new();
+
+    for date in list(data_path)? {
+        if date < oldest {
+            fs::remove_dir_all(snapshot_path(data_path, &date.to_string()))?;
+            removed.push(date);
+        }
+    }
+
+    Ok(removed)
+}
+
+
+#[test]
+fn test_snapshots() {
+    let data_path = std::env::temp_dir().join(format!("sync-snapshot-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&data_path);
+
+    let dates = days(Date::new(2025, 12, 30).unwrap(), Date::new(2026, 1, 2).unwrap());
+    assert_eq!(dates.iter().map(|date| date.number()).collect::<Vec<u32>>(), vec![20251230, 20251231, 20260101, 20260102]);
+    assert!(list(&data_path).unwrap().is_empty());
+
+    let sources = Sources::builtin();
+    for date in dates.iter() {
+        let dir = snapshot_path(&data_path, &date.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("delegated-apnic-latest"), b"").unwrap();
+    }
+    fs::create_dir_all(data_path.join("snapshots").join("not-a-date")).unwrap();
+    assert_eq!(list(&data_path).unwrap(), dates);
+    assert!(!is_complete(&data_path, &sources, dates[0]));
+
+    for source in sources.enabled() {
+        fs::write(snapshot_path(&data_path, "20251230").join(&source.name), b"").unwrap();
+    }
+    assert!(is_complete(&data_path, &sources, dates[0]));
+
+    assert_eq!(prune(&data_path, dates[2]).unwrap(), &dates[..2]);
+    assert_eq!(list(&data_path).unwrap(), &dates[2..]);
+
+    fs::remove_dir_all(&data_path).unwrap();
+}
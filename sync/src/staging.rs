@@ -0,0 +1,234 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Write `content` to a temp file next to `path`, fsync it and rename it
+/// over `path`, readers see either the old or the new file.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let filename = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    let tmp_path = path.with_file_name(format!(".{}.tmp", filename));
+
+    let ret = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, path)
+    })();
+    if ret.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    ret?;
+
+    sync_dir(path.parent().unwrap_or(Path::new(".")))
+}
+
+// Persist the renames in `dir`.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    // NOTE: Windows 上无法打开目录，rename 本身已经足够。
+    if cfg!(unix) {
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+
+// Held while a generation is built, see `Staging::new`.
+#[derive(Debug)]
+struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    fn acquire(path: PathBuf) -> io::Result<Self> {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                writeln!(file, "{}", std::process::id())?;
+                Ok(Lock { path })
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                                   format!("{:?} exists, another sync is running, remove it if that sync was killed", path)))
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+// NOTE: 未修改的文件用硬链接，不支持硬链接的文件系统上复制。
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::hard_link(from, to).is_err() {
+        fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
+
+
+/// A generation of synced files, built in `<data_path>/.generations/<id>`
+/// and switched in by pointing the `<data_path>/current` symlink at it, see
+/// `sources::current_path`.
+///
+/// A generation starts with every file of the current one, downloads
+/// replace them. Readers of `current` see the whole old generation or the
+/// whole new one, never a mix. `<data_path>/.lock` is held until the
+/// generation is promoted or discarded, so overlapping syncs fail instead of
+/// destroying each other's generation.
+#[derive(Debug)]
+pub struct Staging {
+    data_path: PathBuf,
+    path: PathBuf,
+    previous: Option<PathBuf>,
+    written: Mutex<Vec<String>>,
+    _lock: Lock,
+}
+
+impl Staging {
+    pub fn new(data_path: &Path) -> io::Result<Self> {
+        let lock = Lock::acquire(data_path.join(".lock"))?;
+
+        let generations = data_path.join(".generations");
+        fs::create_dir_all(&generations)?;
+
+        let previous = fs::read_link(data_path.join("current")).ok().map(|target| data_path.join(target));
+        let id = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let path = generations.join(id.to_string());
+        fs::create_dir(&path)?;
+
+        if let Some(ref previous) = previous {
+            for entry in fs::read_dir(previous)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    link_or_copy(&entry.path(), &path.join(entry.file_name()))?;
+                }
+            }
+        }
+
+        Ok(Staging { data_path: data_path.to_path_buf(), path, previous, written: Mutex::new(Vec::new()), _lock: lock })
+    }
+
+    /// Directory of the current generation, `None` before the first sync.
+    pub fn previous(&self) -> Option<&Path> {
+        self.previous.as_deref()
+    }
+
+    pub fn write(&self, filename: &str, content: &[u8]) -> io::Result<()> {
+        write_atomic(&self.path.join(filename), content)?;
+        self.written.lock().unwrap().push(filename.to_string());
+
+        Ok(())
+    }
+
+    /// Make this generation the current one in one `rename` of the `current`
+    /// symlink, returns the names of the files written to it.
+    ///
+    /// Generations other than this one and the previous one, which a reader
+    /// may still be using, are removed.
+    pub fn promote(self) -> io::Result<Vec<String>> {
+        sync_dir(&self.path)?;
+
+        let target = self.path.strip_prefix(&self.data_path).unwrap_or(&self.path);
+        let tmp_link = self.data_path.join(".current.tmp");
+        let _ = fs::remove_file(&tmp_link);
+        symlink_dir(target, &tmp_link)?;
+        fs::rename(&tmp_link, self.data_path.join("current"))?;
+        sync_dir(&self.data_path)?;
+
+        // NOTE: 持有锁时没有其它同步，其它的代都是旧的或者中断的同步留下的。
+        for entry in fs::read_dir(self.data_path.join(".generations"))? {
+            let path = entry?.path();
+            if path != self.path && Some(&path) != self.previous.as_ref() {
+                fs::remove_dir_all(&path)?;
+            }
+        }
+
+        let mut written = self.written.lock().unwrap().clone();
+        written.sort();
+        written.dedup();
+
+        Ok(written)
+    }
+
+    /// Drop this generation, the current one is left untouched.
+    pub fn discard(self) -> io::Result<()> {
+        fs::remove_dir_all(&self.path)?;
+        // NOTE: 第一次同步失败时不留下空目录。
+        let _ = fs::remove_dir(self.data_path.join(".generations"));
+
+        Ok(())
+    }
+}
+
+
+#[test]
+fn test_staging() {
+    let data_path = std::env::temp_dir().join(format!("sync-staging-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&data_path);
+    fs::create_dir(&data_path).unwrap();
+
+    write_atomic(&data_path.join("delegated-apnic-latest"), b"old content, longer than the new one").unwrap();
+    write_atomic(&data_path.join("delegated-apnic-latest"), b"new").unwrap();
+    assert_eq!(fs::read(data_path.join("delegated-apnic-latest")).unwrap(), b"new");
+    assert!(!data_path.join(".delegated-apnic-latest.tmp").exists());
+    fs::remove_file(data_path.join("delegated-apnic-latest")).unwrap();
+
+    let staging = Staging::new(&data_path).unwrap();
+    assert!(staging.previous().is_none());
+    // 同时只能有一个同步
+    assert_eq!(Staging::new(&data_path).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    staging.write("delegated-apnic-latest", b"discarded").unwrap();
+    staging.discard().unwrap();
+    assert_eq!(fs::read_dir(&data_path).unwrap().count(), 0);
+
+    let staging = Staging::new(&data_path).unwrap();
+    staging.write("delegated-apnic-latest.md5", b"md5").unwrap();
+    staging.write("delegated-apnic-latest.http", b"etag: \"1\"\n").unwrap();
+    staging.write("delegated-apnic-latest", b"promoted").unwrap();
+    staging.write("delegated-lacnic-latest", b"lacnic").unwrap();
+    assert_eq!(staging.promote().unwrap(),
+               vec!["delegated-apnic-latest", "delegated-apnic-latest.http", "delegated-apnic-latest.md5", "delegated-lacnic-latest"]);
+    let current = data_path.join("current");
+    assert_eq!(fs::read(current.join("delegated-apnic-latest")).unwrap(), b"promoted");
+    assert!(!data_path.join(".lock").exists());
+
+    // 新的一代包含未修改的文件，替换之前旧的一代保持不变
+    let first = fs::canonicalize(&current).unwrap();
+    let staging = Staging::new(&data_path).unwrap();
+    assert_eq!(staging.previous().map(|path| fs::canonicalize(path).unwrap()), Some(first.clone()));
+    staging.write("delegated-apnic-latest", b"second").unwrap();
+    assert_eq!(fs::read(current.join("delegated-apnic-latest")).unwrap(), b"promoted");
+    assert_eq!(staging.promote().unwrap(), vec!["delegated-apnic-latest"]);
+    assert_eq!(fs::read(current.join("delegated-apnic-latest")).unwrap(), b"second");
+    assert_eq!(fs::read(current.join("delegated-lacnic-latest")).unwrap(), b"lacnic");
+    assert_eq!(fs::read(first.join("delegated-apnic-latest")).unwrap(), b"promoted");
+
+    // 只保留当前和上一代
+    let second = fs::canonicalize(&current).unwrap();
+    let staging = Staging::new(&data_path).unwrap();
+    staging.promote().unwrap();
+    assert!(!first.exists());
+    assert!(second.exists());
+    assert_eq!(fs::read_dir(data_path.join(".generations")).unwrap().count(), 2);
+
+    fs::remove_dir_all(&data_path).unwrap();
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::path::Path;
+
+
+/// HTTP cache validators of a downloaded file, kept in a `<name>.http`
+/// sidecar file next to it:
+///
+///      url: https://ftp.apnic.net/stats/apnic/delegated-apnic-latest
+///      etag: "5f2b-63a1c0d2e4f80"
+///      last-modified: Fri, 16 Oct 2026 00:12:31 GMT
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// The URL that returned them, validators of a server mean nothing to its
+    /// mirrors.
+    pub url: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn sidecar_name(filename: &str) -> String {
+        format!("{}.http", filename)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut validators = Validators::default();
+
+        for line in content.lines() {
+            let mut kv = line.splitn(2, ':');
+            let key = kv.next().unwrap_or("").trim();
+            let value = kv.next().map(|s| s.trim()).filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+            match key {
+                "url" => validators.url = value,
+                "etag" => validators.etag = value,
+                "last-modified" => validators.last_modified = value,
+                _ => { },
+            }
+        }
+
+        validators
+    }
+
+    /// The validators of a file in `data_path`, empty if there are none.
+    pub fn load(data_path: &Path, filename: &str) -> Self {
+        match std::fs::read_to_string(data_path.join(Validators::sidecar_name(filename))) {
+            Ok(content) => Validators::parse(&content),
+            Err(_) => Validators::default(),
+        }
+    }
+
+    /// These validators if they came from `url`, otherwise none.
+    pub fn for_url(self, url: &str) -> Self {
+        // NOTE: 镜像的 ETag 可能和主站的碰巧相同，返回错误的 304。
+        if self.url.as_deref() == Some(url) {
+            self
+        } else {
+            Validators::default()
+        }
+    }
+
+    /// Read the validators of a response to `url`.
+    pub fn from_headers(url: &str, headers: &hyper::HeaderMap) -> Self {
+        let header = |name: hyper::header::HeaderName| {
+            headers.get(name).and_then(|value| value.to_str().ok()).map(|value| value.to_string())
+        };
+
+        Validators {
+            url: Some(url.to_string()),
+            etag: header(hyper::header::ETAG),
+            last_modified: header(hyper::header::LAST_MODIFIED),
+        }
+    }
+
+    /// Add `If-None-Match` and `If-Modified-Since` to a request.
+    pub fn apply(&self, mut builder: hyper::http::request::Builder) -> hyper::http::request::Builder {
+        if let Some(ref etag) = self.etag {
+            builder = builder.header(hyper::header::IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            builder = builder.header(hyper::header::IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+
+        builder
+    }
+}
+
+impl fmt::Display for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref url) = self.url {
+            writeln!(f, "url: {}", url)?;
+        }
+        if let Some(ref etag) = self.etag {
+            writeln!(f, "etag: {}", etag)?;
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            writeln!(f, "last-modified: {}", last_modified)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[test]
+fn test_validators() {
+    let validators = Validators {
+        url: Some("https://ftp.apnic.net/stats/apnic/delegated-apnic-latest".to_string()),
+        etag: Some("\"5f2b-63a1c0d2e4f80\"".to_string()),
+        last_modified: Some("Fri, 16 Oct 2026 00:12:31 GMT".to_string()),
+    };
+    let content = validators.to_string();
+    assert_eq!(content, "url: https://ftp.apnic.net/stats/apnic/delegated-apnic-latest\n\
+                         etag: \"5f2b-63a1c0d2e4f80\"\nlast-modified: Fri, 16 Oct 2026 00:12:31 GMT\n");
+    assert_eq!(Validators::parse(&content), validators);
+
+    assert_eq!(validators.clone().for_url("https://ftp.apnic.net/stats/apnic/delegated-apnic-latest"), validators);
+    assert!(validators.clone().for_url("https://mirror.example.net/apnic/delegated-apnic-latest").is_empty());
+    // Sidecar files written before the URL was kept
+    assert!(Validators::parse("etag: \"1\"\n").for_url("https://ftp.apnic.net/").is_empty());
+
+    assert!(Validators::parse("").is_empty());
+    assert_eq!(Validators::parse("etag: W/\"1\"\nvary: *\n").etag, Some("W/\"1\"".to_string()));
+
+    let request = validators.apply(hyper::Request::get("https://ftp.apnic.net/")).body(()).unwrap();
+    assert_eq!(request.headers()[hyper::header::IF_NONE_MATCH], "\"5f2b-63a1c0d2e4f80\"");
+    assert_eq!(request.headers()[hyper::header::IF_MODIFIED_SINCE], "Fri, 16 Oct 2026 00:12:31 GMT");
+}